@@ -0,0 +1,326 @@
+use chrono::{DateTime, Utc};
+use clap::{Parser, Subcommand};
+use config::ReceiverConfig;
+use rdkafka::admin::{AdminClient, AdminOptions, NewTopic, TopicReplication, TopicResult};
+use rdkafka::client::DefaultClientContext;
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::{BaseConsumer, CommitMode, Consumer};
+use rdkafka::error::{KafkaResult, RDKafkaErrorCode};
+use rdkafka::metadata::{Metadata, MetadataTopic};
+use rdkafka::{Offset, TopicPartitionList};
+use serde::Serialize;
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Parser, Debug)]
+#[command(about = "Kafka operations for the sender and receiver topics and groups")]
+struct Args {
+    /// Receiver configuration file (TOML) to take the brokers, group and
+    /// topic from
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    #[command(flatten)]
+    overrides: Overrides,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// Command-line settings, applied over the config file and environment.
+#[derive(clap::Args, Serialize, Debug)]
+struct Overrides {
+    /// Kafka bootstrap servers
+    #[arg(long = "brokers")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kafka_brokers: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Create a topic
+    CreateTopic {
+        topic: String,
+        #[arg(long, default_value_t = 3)]
+        partitions: i32,
+        #[arg(long, default_value_t = 1)]
+        replication_factor: i32,
+    },
+    /// Show a topic's partitions, leaders and replicas
+    DescribeTopic { topic: String },
+    /// Delete a topic and all of its messages
+    DeleteTopic {
+        topic: String,
+        /// Delete the topic; without this only its partitions are shown
+        #[arg(long)]
+        execute: bool,
+    },
+    /// List consumer groups and their state
+    ListGroups,
+    /// Show a group's committed offset and lag for each partition
+    Lag {
+        /// Consumer group; defaults to the receiver's
+        #[arg(long)]
+        group: Option<String>,
+        /// Topic; defaults to the receiver's
+        #[arg(long)]
+        topic: Option<String>,
+    },
+    /// Move a group's committed offsets to the first message at or after a
+    /// time. The group must have no running consumers.
+    ResetOffsets {
+        /// Consumer group; defaults to the receiver's
+        #[arg(long)]
+        group: Option<String>,
+        /// Topic; defaults to the receiver's
+        #[arg(long)]
+        topic: Option<String>,
+        /// RFC 3339 time, e.g. 2024-05-01T12:00:00Z
+        #[arg(long)]
+        to: DateTime<Utc>,
+        /// Commit the new offsets; without this only the plan is printed
+        #[arg(long)]
+        execute: bool,
+    },
+}
+
+fn admin_client(brokers: &str) -> KafkaResult<AdminClient<DefaultClientContext>> {
+    // Metadata requests for a missing topic must not create it, or a typo
+    // in a dry run would leave a new topic behind
+    ClientConfig::new()
+        .set("bootstrap.servers", brokers)
+        .set("allow.auto.create.topics", "false")
+        .create()
+}
+
+/// Consumer acting on behalf of `group`. It never subscribes, so it does
+/// not join the group or disturb running members.
+fn group_consumer(brokers: &str, group: &str) -> KafkaResult<BaseConsumer> {
+    ClientConfig::new()
+        .set("bootstrap.servers", brokers)
+        .set("group.id", group)
+        .set("enable.auto.commit", "false")
+        .create()
+}
+
+fn check_results(results: Vec<TopicResult>, action: &str) -> Result<(), Box<dyn Error>> {
+    for result in results {
+        match result {
+            Ok(topic) => println!("{} topic {}", action, topic),
+            Err((topic, code)) => return Err(format!("topic {}: {}", topic, code).into()),
+        }
+    }
+    Ok(())
+}
+
+/// Metadata for `topic`, failing if the broker reported an error for it.
+fn find_topic<'a>(
+    metadata: &'a Metadata,
+    topic: &str,
+) -> Result<&'a MetadataTopic, Box<dyn Error>> {
+    let topic_metadata = metadata
+        .topics()
+        .first()
+        .ok_or_else(|| format!("no metadata returned for topic {}", topic))?;
+    if let Some(e) = topic_metadata.error() {
+        return Err(format!("topic {}: {}", topic, RDKafkaErrorCode::from(e)).into());
+    }
+    Ok(topic_metadata)
+}
+
+fn topic_partitions(consumer: &BaseConsumer, topic: &str) -> Result<Vec<i32>, Box<dyn Error>> {
+    let metadata = consumer.fetch_metadata(Some(topic), TIMEOUT)?;
+    let topic_metadata = find_topic(&metadata, topic)?;
+    Ok(topic_metadata.partitions().iter().map(|p| p.id()).collect())
+}
+
+async fn create_topic(
+    brokers: &str,
+    topic: &str,
+    partitions: i32,
+    replication_factor: i32,
+) -> Result<(), Box<dyn Error>> {
+    let admin = admin_client(brokers)?;
+    let new_topic = NewTopic::new(
+        topic,
+        partitions,
+        TopicReplication::Fixed(replication_factor),
+    );
+    let options = AdminOptions::new().operation_timeout(Some(TIMEOUT));
+    check_results(
+        admin.create_topics([&new_topic], &options).await?,
+        "Created",
+    )
+}
+
+async fn delete_topic(brokers: &str, topic: &str, execute: bool) -> Result<(), Box<dyn Error>> {
+    if !execute {
+        describe_topic(brokers, topic)?;
+        println!(
+            "Dry run; pass --execute to delete topic {} and all of its messages",
+            topic
+        );
+        return Ok(());
+    }
+
+    let admin = admin_client(brokers)?;
+    let options = AdminOptions::new().operation_timeout(Some(TIMEOUT));
+    check_results(admin.delete_topics(&[topic], &options).await?, "Deleted")
+}
+
+fn describe_topic(brokers: &str, topic: &str) -> Result<(), Box<dyn Error>> {
+    let admin = admin_client(brokers)?;
+    let metadata = admin.inner().fetch_metadata(Some(topic), TIMEOUT)?;
+    let topic_metadata = find_topic(&metadata, topic)?;
+
+    println!(
+        "Topic {} ({} partitions)",
+        topic,
+        topic_metadata.partitions().len()
+    );
+    for partition in topic_metadata.partitions() {
+        println!(
+            "  partition {}: leader={} replicas={:?} isr={:?}",
+            partition.id(),
+            partition.leader(),
+            partition.replicas(),
+            partition.isr()
+        );
+    }
+    Ok(())
+}
+
+fn list_groups(brokers: &str) -> Result<(), Box<dyn Error>> {
+    let admin = admin_client(brokers)?;
+    let groups = admin.inner().fetch_group_list(None, TIMEOUT)?;
+    if groups.groups().is_empty() {
+        println!("No consumer groups");
+    }
+    for group in groups.groups() {
+        println!(
+            "{} state={} members={}",
+            group.name(),
+            group.state(),
+            group.members().len()
+        );
+    }
+    Ok(())
+}
+
+fn show_lag(brokers: &str, group: &str, topic: &str) -> Result<(), Box<dyn Error>> {
+    let consumer = group_consumer(brokers, group)?;
+    let mut partitions = TopicPartitionList::new();
+    for partition in topic_partitions(&consumer, topic)? {
+        partitions.add_partition(topic, partition);
+    }
+    let committed = consumer.committed_offsets(partitions, TIMEOUT)?;
+
+    println!("Group {} on topic {}", group, topic);
+    println!(
+        "{:>9} {:>12} {:>12} {:>10}",
+        "PARTITION", "COMMITTED", "END", "LAG"
+    );
+    let mut total = 0;
+    for elem in committed.elements() {
+        let (low, high) = consumer.fetch_watermarks(topic, elem.partition(), TIMEOUT)?;
+        // Without a commit the group starts from the earliest message
+        let (committed, lag) = match elem.offset() {
+            Offset::Offset(offset) => (offset.to_string(), high - offset),
+            _ => ("-".to_string(), high - low),
+        };
+        total += lag;
+        println!(
+            "{:>9} {:>12} {:>12} {:>10}",
+            elem.partition(),
+            committed,
+            high,
+            lag
+        );
+    }
+    println!("Total lag: {}", total);
+    Ok(())
+}
+
+fn reset_offsets(
+    brokers: &str,
+    group: &str,
+    topic: &str,
+    to: DateTime<Utc>,
+    execute: bool,
+) -> Result<(), Box<dyn Error>> {
+    let consumer = group_consumer(brokers, group)?;
+    let mut query = TopicPartitionList::new();
+    for partition in topic_partitions(&consumer, topic)? {
+        query.add_partition_offset(topic, partition, Offset::Offset(to.timestamp_millis()))?;
+    }
+    let found = consumer.offsets_for_times(query, TIMEOUT)?;
+
+    println!("Offsets for group {} on topic {} at {}", group, topic, to);
+    let mut offsets = TopicPartitionList::new();
+    for elem in found.elements() {
+        // No message at or after the time: move to the end of the partition
+        let offset = match elem.offset() {
+            Offset::Offset(offset) => offset,
+            _ => {
+                consumer
+                    .fetch_watermarks(topic, elem.partition(), TIMEOUT)?
+                    .1
+            }
+        };
+        println!("  partition {}: {}", elem.partition(), offset);
+        offsets.add_partition_offset(topic, elem.partition(), Offset::Offset(offset))?;
+    }
+
+    if !execute {
+        println!("Dry run; pass --execute to commit these offsets");
+        return Ok(());
+    }
+    consumer.commit(&offsets, CommitMode::Sync)?;
+    println!("Committed offsets for group {}", group);
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+
+    // Same layers as the receiver, so the defaults follow its settings
+    let ReceiverConfig {
+        kafka_brokers,
+        kafka_topic,
+        consumer_group,
+        ..
+    } = config::load(args.config.as_deref(), &args.overrides)?;
+    let brokers = kafka_brokers.as_str();
+
+    match args.command {
+        Command::CreateTopic {
+            topic,
+            partitions,
+            replication_factor,
+        } => create_topic(brokers, &topic, partitions, replication_factor).await,
+        Command::DescribeTopic { topic } => describe_topic(brokers, &topic),
+        Command::DeleteTopic { topic, execute } => delete_topic(brokers, &topic, execute).await,
+        Command::ListGroups => list_groups(brokers),
+        Command::Lag { group, topic } => show_lag(
+            brokers,
+            &group.unwrap_or(consumer_group),
+            &topic.unwrap_or(kafka_topic),
+        ),
+        Command::ResetOffsets {
+            group,
+            topic,
+            to,
+            execute,
+        } => reset_offsets(
+            brokers,
+            &group.unwrap_or(consumer_group),
+            &topic.unwrap_or(kafka_topic),
+            to,
+            execute,
+        ),
+    }
+}
@@ -0,0 +1,276 @@
+use crate::{
+    payload, BusError, Delivery, MessageBus, Outgoing, PartitionLag, Receipt, Subscription,
+};
+use rdkafka::client::ClientContext;
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::{CommitMode, Consumer, ConsumerContext, StreamConsumer};
+use rdkafka::error::{KafkaError, KafkaResult};
+use rdkafka::message::{Header, Headers, OwnedHeaders};
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::{Message, Offset, Statistics, TopicPartitionList};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+/// How often librdkafka reports the statistics the queue depth and lag are
+/// read from.
+const STATISTICS_INTERVAL_MS: &str = "1000";
+
+/// Keeps the figures the bus reports from librdkafka's periodic statistics,
+/// so reading them never calls the broker. Clones share the same figures.
+#[derive(Clone, Default)]
+struct StatsContext {
+    queued: Arc<AtomicI64>,
+    lag: Arc<Mutex<HashMap<(String, i32), i64>>>,
+}
+
+impl ClientContext for StatsContext {
+    fn stats(&self, statistics: Statistics) {
+        self.queued
+            .store(statistics.msg_cnt as i64, Ordering::Relaxed);
+
+        // Replaced wholesale, so partitions dropped from the report go too
+        let mut lag = HashMap::new();
+        for (name, topic) in statistics.topics {
+            for partition in topic.partitions.into_values() {
+                // -1 until the group has a committed offset on the partition
+                if partition.consumer_lag >= 0 {
+                    lag.insert((name.clone(), partition.partition), partition.consumer_lag);
+                }
+            }
+        }
+        *self.lag.lock().unwrap() = lag;
+    }
+}
+
+impl ConsumerContext for StatsContext {}
+
+/// Kafka backend. The producer is created on first publish, so services
+/// that only consume never open one.
+pub struct KafkaBus {
+    brokers: String,
+    message_timeout_ms: u64,
+    producer_stats: StatsContext,
+    producer: OnceLock<KafkaResult<FutureProducer<StatsContext>>>,
+}
+
+impl KafkaBus {
+    pub fn new(brokers: &str) -> Self {
+        Self {
+            brokers: brokers.to_string(),
+            message_timeout_ms: 5000,
+            producer_stats: StatsContext::default(),
+            producer: OnceLock::new(),
+        }
+    }
+
+    /// How long the producer retries a message before reporting it failed.
+    pub fn with_message_timeout(mut self, message_timeout_ms: u64) -> Self {
+        self.message_timeout_ms = message_timeout_ms;
+        self
+    }
+
+    fn producer(&self) -> Result<&FutureProducer<StatsContext>, BusError> {
+        let producer = self.producer.get_or_init(|| {
+            ClientConfig::new()
+                .set("bootstrap.servers", &self.brokers)
+                .set("message.timeout.ms", self.message_timeout_ms.to_string())
+                .set("acks", "all")
+                .set("retries", "3")
+                .set("statistics.interval.ms", STATISTICS_INTERVAL_MS)
+                .create_with_context(self.producer_stats.clone())
+        });
+        producer.as_ref().map_err(|e| e.clone().into())
+    }
+}
+
+impl From<KafkaError> for BusError {
+    fn from(e: KafkaError) -> Self {
+        Self(Box::new(e))
+    }
+}
+
+/// Kafka headers carrying `headers`.
+fn record_headers(headers: &[(String, String)]) -> OwnedHeaders {
+    headers
+        .iter()
+        .fold(OwnedHeaders::new(), |record_headers, (key, value)| {
+            record_headers.insert(Header {
+                key,
+                value: Some(value),
+            })
+        })
+}
+
+/// Text headers of a record; null and non-UTF-8 values are skipped.
+fn text_headers<H: Headers>(headers: Option<&H>) -> Vec<(String, String)> {
+    let Some(headers) = headers else {
+        return Vec::new();
+    };
+    headers
+        .iter()
+        .filter_map(|h| {
+            let value = std::str::from_utf8(h.value?).ok()?;
+            Some((h.key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Record key as text; `None` when absent or not UTF-8.
+fn text_key(key: Option<&[u8]>) -> Option<String> {
+    key.and_then(|key| std::str::from_utf8(key).ok())
+        .map(str::to_string)
+}
+
+impl MessageBus for KafkaBus {
+    type Subscription = KafkaSubscription;
+
+    async fn publish(&self, topic: &str, message: Outgoing) -> Result<Receipt, BusError> {
+        let producer = self.producer()?;
+
+        let record = FutureRecord::to(topic)
+            .key(&message.key)
+            .payload(&message.payload)
+            .headers(record_headers(&message.headers));
+
+        let (partition, offset) = producer
+            .send(record, Duration::from_secs(5))
+            .await
+            .map_err(|(e, _)| e)?;
+        Ok(Receipt { partition, offset })
+    }
+
+    fn in_flight(&self) -> i64 {
+        self.producer_stats.queued.load(Ordering::Relaxed)
+    }
+
+    async fn subscribe(&self, topic: &str, group: &str) -> Result<KafkaSubscription, BusError> {
+        let consumer: StreamConsumer<StatsContext> = ClientConfig::new()
+            .set("group.id", group)
+            .set("bootstrap.servers", &self.brokers)
+            .set("enable.partition.eof", "false")
+            .set("session.timeout.ms", "6000")
+            .set("enable.auto.commit", "false")
+            .set("auto.offset.reset", "earliest")
+            .set("statistics.interval.ms", STATISTICS_INTERVAL_MS)
+            .create_with_context(StatsContext::default())?;
+        consumer.subscribe(&[topic])?;
+        Ok(KafkaSubscription { consumer })
+    }
+}
+
+pub struct KafkaSubscription {
+    consumer: StreamConsumer<StatsContext>,
+}
+
+impl Subscription for KafkaSubscription {
+    // Topic, partition and offset are enough to commit
+    type AckToken = ();
+
+    async fn recv(&mut self) -> Result<Delivery<()>, BusError> {
+        let m = self.consumer.recv().await?;
+        Ok(Delivery {
+            topic: m.topic().to_string(),
+            partition: m.partition(),
+            offset: m.offset(),
+            key: text_key(m.key()),
+            payload: m.payload().and_then(payload),
+            headers: text_headers(m.headers()),
+            token: (),
+        })
+    }
+
+    async fn ack(&self, delivery: &Delivery<()>) -> Result<(), BusError> {
+        let mut offsets = TopicPartitionList::new();
+        offsets.add_partition_offset(
+            &delivery.topic,
+            delivery.partition,
+            Offset::Offset(delivery.offset + 1),
+        )?;
+        self.consumer.commit(&offsets, CommitMode::Async)?;
+        Ok(())
+    }
+
+    // Kafka cannot reject a single message; committing past it keeps the
+    // group from reading it again.
+    async fn reject(&self, delivery: &Delivery<()>) -> Result<(), BusError> {
+        self.ack(delivery).await
+    }
+
+    // The statistics also cover partitions this member no longer owns, so
+    // only the current assignment is reported.
+    async fn lag(&self) -> Result<Vec<PartitionLag>, BusError> {
+        let assignment = self.consumer.assignment()?;
+        let lag = self.consumer.context().lag.lock().unwrap();
+        Ok(assignment
+            .elements()
+            .iter()
+            .filter_map(|tp| {
+                let topic = tp.topic().to_string();
+                let lag = *lag.get(&(topic.clone(), tp.partition()))?;
+                Some(PartitionLag {
+                    topic,
+                    partition: tp.partition(),
+                    lag,
+                })
+            })
+            .collect())
+    }
+
+    /// Pauses the current assignment. Pauses longer than
+    /// `max.poll.interval.ms` make the consumer leave its group, just as a
+    /// stalled process would.
+    fn pause(&self) -> Result<(), BusError> {
+        self.consumer.pause(&self.consumer.assignment()?)?;
+        Ok(())
+    }
+
+    fn resume(&self) -> Result<(), BusError> {
+        self.consumer.resume(&self.consumer.assignment()?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headers_round_trip() {
+        let headers = vec![
+            ("traceparent".to_string(), "00-abc-def-01".to_string()),
+            ("tenant".to_string(), "".to_string()),
+        ];
+        assert_eq!(text_headers(Some(&record_headers(&headers))), headers);
+    }
+
+    #[test]
+    fn skips_headers_that_are_not_text() {
+        let headers = OwnedHeaders::new()
+            .insert(Header {
+                key: "null",
+                value: None::<&[u8]>,
+            })
+            .insert(Header {
+                key: "binary",
+                value: Some(&[0xff, 0xfe][..]),
+            })
+            .insert(Header {
+                key: "text",
+                value: Some("yes"),
+            });
+        assert_eq!(
+            text_headers(Some(&headers)),
+            [("text".to_string(), "yes".to_string())]
+        );
+        assert!(text_headers::<OwnedHeaders>(None).is_empty());
+    }
+
+    #[test]
+    fn key_must_be_text() {
+        assert_eq!(text_key(Some(b"order-1")), Some("order-1".to_string()));
+        assert_eq!(text_key(Some(&[0xff][..])), None);
+        assert_eq!(text_key(None), None);
+    }
+}
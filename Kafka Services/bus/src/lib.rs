@@ -0,0 +1,162 @@
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+
+#[cfg(feature = "kafka")]
+mod kafka;
+#[cfg(feature = "nats")]
+mod nats;
+
+#[cfg(feature = "kafka")]
+pub use kafka::{KafkaBus, KafkaSubscription};
+#[cfg(feature = "nats")]
+pub use nats::{NatsBus, NatsSubscription};
+
+/// Error from any backend, boxed so callers need not depend on the client
+/// crates.
+pub struct BusError(Box<dyn Error + Send + Sync>);
+
+impl fmt::Display for BusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Debug for BusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl Error for BusError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.0.source()
+    }
+}
+
+impl From<&str> for BusError {
+    fn from(message: &str) -> Self {
+        Self(message.into())
+    }
+}
+
+impl From<Box<dyn Error + Send + Sync>> for BusError {
+    fn from(e: Box<dyn Error + Send + Sync>) -> Self {
+        Self(e)
+    }
+}
+
+/// Message handed to [`MessageBus::publish`].
+#[derive(Debug, Clone)]
+pub struct Outgoing {
+    pub key: String,
+    pub payload: Vec<u8>,
+    /// Text headers, such as the trace context
+    pub headers: Vec<(String, String)>,
+}
+
+/// Where a published message was stored.
+#[derive(Debug, Clone, Copy)]
+pub struct Receipt {
+    pub partition: i32,
+    pub offset: i64,
+}
+
+/// Message read from a [`Subscription`], to be acknowledged through it.
+/// `T` is the subscription's [`Subscription::AckToken`].
+pub struct Delivery<T> {
+    pub topic: String,
+    pub partition: i32,
+    pub offset: i64,
+    pub key: Option<String>,
+    /// `None` for an empty message, on every backend
+    pub payload: Option<Vec<u8>>,
+    pub headers: Vec<(String, String)>,
+    // Kafka acknowledges by position alone; only NATS reads its token
+    #[cfg_attr(not(feature = "nats"), allow(dead_code))]
+    token: T,
+}
+
+/// Payload of a received message; empty ones, which Kafka reports as
+/// present and NATS cannot tell from absent, are `None` everywhere.
+#[cfg_attr(not(any(feature = "kafka", feature = "nats")), allow(dead_code))]
+fn payload(bytes: &[u8]) -> Option<Vec<u8>> {
+    (!bytes.is_empty()).then(|| bytes.to_vec())
+}
+
+/// Messages on an assigned partition the group has yet to process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionLag {
+    pub topic: String,
+    pub partition: i32,
+    pub lag: i64,
+}
+
+/// Produce/consume layer the services run on, so the same pipeline works
+/// against any backend.
+///
+/// Backends without partitions report every message on partition 0, with
+/// its position in the stream as the offset.
+pub trait MessageBus: Send + Sync {
+    type Subscription: Subscription;
+
+    /// Resolves once the backend has stored the message.
+    fn publish(
+        &self,
+        topic: &str,
+        message: Outgoing,
+    ) -> impl Future<Output = Result<Receipt, BusError>> + Send;
+
+    /// Messages published but not yet acknowledged by the backend. Cheap to
+    /// call; backends may report a value a few seconds old.
+    fn in_flight(&self) -> i64;
+
+    /// Joins `group`; its members share the topic's messages and resume
+    /// from the group's last acknowledged message.
+    fn subscribe(
+        &self,
+        topic: &str,
+        group: &str,
+    ) -> impl Future<Output = Result<Self::Subscription, BusError>> + Send;
+}
+
+pub trait Subscription: Send {
+    /// Backend state a delivery carries so it can be acknowledged.
+    type AckToken: Send + Sync;
+
+    fn recv(&mut self) -> impl Future<Output = Result<Delivery<Self::AckToken>, BusError>> + Send;
+
+    /// Marks the delivery as processed for the group.
+    fn ack(
+        &self,
+        delivery: &Delivery<Self::AckToken>,
+    ) -> impl Future<Output = Result<(), BusError>> + Send;
+
+    /// Marks the delivery as one that can never be processed, such as an
+    /// undecodable message, so it is not delivered to the group again.
+    fn reject(
+        &self,
+        delivery: &Delivery<Self::AckToken>,
+    ) -> impl Future<Output = Result<(), BusError>> + Send;
+
+    /// Lag of each partition currently assigned to this member, as last
+    /// reported by the backend. Partitions with no lag figure yet are left
+    /// out.
+    fn lag(&self) -> impl Future<Output = Result<Vec<PartitionLag>, BusError>> + Send;
+
+    /// Stops fetching new messages until [`Subscription::resume`].
+    fn pause(&self) -> Result<(), BusError>;
+
+    fn resume(&self) -> Result<(), BusError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_payload_is_none() {
+        assert_eq!(payload(b""), None);
+        assert_eq!(payload(b"{}"), Some(b"{}".to_vec()));
+    }
+}
@@ -0,0 +1,220 @@
+use crate::{
+    payload, BusError, Delivery, MessageBus, Outgoing, PartitionLag, Receipt, Subscription,
+};
+use async_nats::jetstream::consumer::{pull, PullConsumer};
+use async_nats::jetstream::message::{AckKind, Acker};
+use async_nats::jetstream::{self, stream};
+use async_nats::HeaderMap;
+use futures::StreamExt;
+use std::collections::HashSet;
+use std::fmt::{Debug, Display};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
+
+/// Header carrying the message key, which NATS messages have no field for.
+const KEY_HEADER: &str = "Message-Key";
+
+/// NATS JetStream backend. Each topic is stored in a stream of the same
+/// name bound to the subject of the same name, created on first use; each
+/// group is a durable pull consumer on that stream.
+pub struct NatsBus {
+    jetstream: jetstream::Context,
+    streams: Mutex<HashSet<String>>,
+    in_flight: AtomicI64,
+}
+
+impl NatsBus {
+    pub async fn connect(url: &str) -> Result<Self, BusError> {
+        let client = async_nats::connect(url).await?;
+        Ok(Self {
+            jetstream: jetstream::new(client),
+            streams: Mutex::new(HashSet::new()),
+            in_flight: AtomicI64::new(0),
+        })
+    }
+
+    async fn stream(&self, topic: &str) -> Result<stream::Stream, BusError> {
+        let stream = self
+            .jetstream
+            .get_or_create_stream(stream::Config {
+                name: topic.to_string(),
+                subjects: vec![topic.to_string()],
+                ..Default::default()
+            })
+            .await?;
+        self.streams.lock().unwrap().insert(topic.to_string());
+        Ok(stream)
+    }
+}
+
+impl<Kind> From<async_nats::error::Error<Kind>> for BusError
+where
+    Kind: Clone + Debug + Display + PartialEq + Send + Sync + 'static,
+{
+    fn from(e: async_nats::error::Error<Kind>) -> Self {
+        Self(Box::new(e))
+    }
+}
+
+/// NATS headers carrying the message key and `headers`.
+fn message_headers(key: &str, headers: &[(String, String)]) -> HeaderMap {
+    let mut header_map = HeaderMap::new();
+    header_map.insert(KEY_HEADER, key);
+    for (name, value) in headers {
+        header_map.insert(name.as_str(), value.as_str());
+    }
+    header_map
+}
+
+/// Splits received headers into the message key and the text headers.
+fn split_headers(headers: Option<&HeaderMap>) -> (Option<String>, Vec<(String, String)>) {
+    let mut key = None;
+    let mut text = Vec::new();
+    for (name, values) in headers.into_iter().flat_map(HeaderMap::iter) {
+        for value in values {
+            if AsRef::<str>::as_ref(name) == KEY_HEADER {
+                key = Some(value.to_string());
+            } else {
+                text.push((name.to_string(), value.to_string()));
+            }
+        }
+    }
+    (key, text)
+}
+
+impl MessageBus for NatsBus {
+    type Subscription = NatsSubscription;
+
+    async fn publish(&self, topic: &str, message: Outgoing) -> Result<Receipt, BusError> {
+        let known = self.streams.lock().unwrap().contains(topic);
+        if !known {
+            self.stream(topic).await?;
+        }
+
+        let headers = message_headers(&message.key, &message.headers);
+
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        let ack = async {
+            self.jetstream
+                .publish_with_headers(topic.to_string(), headers, message.payload.into())
+                .await?
+                .await
+        }
+        .await;
+        self.in_flight.fetch_sub(1, Ordering::Relaxed);
+
+        Ok(Receipt {
+            partition: 0,
+            offset: ack?.sequence as i64,
+        })
+    }
+
+    fn in_flight(&self) -> i64 {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    async fn subscribe(&self, topic: &str, group: &str) -> Result<NatsSubscription, BusError> {
+        let consumer = self
+            .stream(topic)
+            .await?
+            .get_or_create_consumer(
+                group,
+                pull::Config {
+                    durable_name: Some(group.to_string()),
+                    filter_subject: topic.to_string(),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        Ok(NatsSubscription {
+            topic: topic.to_string(),
+            messages: consumer.messages().await?,
+            consumer,
+        })
+    }
+}
+
+pub struct NatsSubscription {
+    topic: String,
+    consumer: PullConsumer,
+    messages: pull::Stream,
+}
+
+impl Subscription for NatsSubscription {
+    type AckToken = Acker;
+
+    async fn recv(&mut self) -> Result<Delivery<Acker>, BusError> {
+        let message = self
+            .messages
+            .next()
+            .await
+            .ok_or("NATS message stream closed")??;
+        let offset = message.info()?.stream_sequence as i64;
+        let (message, acker) = message.split();
+
+        let (key, headers) = split_headers(message.headers.as_ref());
+
+        Ok(Delivery {
+            topic: self.topic.clone(),
+            partition: 0,
+            offset,
+            key,
+            payload: payload(&message.payload),
+            headers,
+            token: acker,
+        })
+    }
+
+    async fn ack(&self, delivery: &Delivery<Acker>) -> Result<(), BusError> {
+        Ok(delivery.token.ack().await?)
+    }
+
+    // Terminating stops JetStream redelivering the message once its ack
+    // wait expires.
+    async fn reject(&self, delivery: &Delivery<Acker>) -> Result<(), BusError> {
+        Ok(delivery.token.ack_with(AckKind::Term).await?)
+    }
+
+    // The durable consumer is the whole group; its messages not yet
+    // delivered plus those delivered but unacknowledged are the lag.
+    async fn lag(&self) -> Result<Vec<PartitionLag>, BusError> {
+        let mut consumer = self.consumer.clone();
+        let info = consumer.info().await?;
+        Ok(vec![PartitionLag {
+            topic: self.topic.clone(),
+            partition: 0,
+            lag: (info.num_pending + info.num_ack_pending as u64) as i64,
+        }])
+    }
+
+    // Pull consumers only fetch while polled, so not calling `recv` is
+    // enough. Unacknowledged messages already fetched are redelivered once
+    // their ack wait expires.
+    fn pause(&self) -> Result<(), BusError> {
+        Ok(())
+    }
+
+    fn resume(&self) -> Result<(), BusError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_travels_in_a_header() {
+        let headers = vec![("traceparent".to_string(), "00-abc-def-01".to_string())];
+        let header_map = message_headers("order-1", &headers);
+        assert_eq!(
+            split_headers(Some(&header_map)),
+            (Some("order-1".to_string()), headers)
+        );
+    }
+
+    #[test]
+    fn message_without_headers_has_no_key() {
+        assert_eq!(split_headers(None), (None, Vec::new()));
+    }
+}
@@ -0,0 +1,327 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{error, info, warn};
+
+/// Faults currently injected into a service.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Faults {
+    /// Percentage of produced messages silently discarded
+    pub drop_percent: f64,
+    /// Delay added before each produce or consume
+    pub latency_ms: u64,
+    /// Whether consumption is paused
+    pub paused: bool,
+}
+
+/// Partial change to [`Faults`]; unset fields keep their current value.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct FaultUpdate {
+    pub drop_percent: Option<f64>,
+    pub latency_ms: Option<u64>,
+    pub paused: Option<bool>,
+}
+
+impl FaultUpdate {
+    fn validate(&self) -> Result<(), String> {
+        match self.drop_percent {
+            Some(p) if !(0.0..=100.0).contains(&p) => {
+                Err(format!("drop_percent must be between 0 and 100, got {}", p))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Timed sequence of fault changes, loaded from a TOML file of `[[step]]`
+/// tables.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Schedule {
+    #[serde(rename = "step")]
+    pub steps: Vec<ScheduleStep>,
+}
+
+/// One `[[step]]` table. The fault fields are listed here rather than
+/// flattened from [`FaultUpdate`], which would stop `deny_unknown_fields`
+/// from catching misspelt keys.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleStep {
+    /// Seconds after the service started
+    pub at_secs: u64,
+    pub drop_percent: Option<f64>,
+    pub latency_ms: Option<u64>,
+    pub paused: Option<bool>,
+}
+
+impl ScheduleStep {
+    pub fn update(&self) -> FaultUpdate {
+        FaultUpdate {
+            drop_percent: self.drop_percent,
+            latency_ms: self.latency_ms,
+            paused: self.paused,
+        }
+    }
+}
+
+impl Schedule {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let text = std::fs::read_to_string(path)?;
+        let mut schedule: Schedule = toml::from_str(&text)?;
+        for step in &schedule.steps {
+            step.update()
+                .validate()
+                .map_err(|e| format!("step at {}s: {}", step.at_secs, e))?;
+        }
+        schedule.steps.sort_by_key(|step| step.at_secs);
+        Ok(schedule)
+    }
+}
+
+/// Shared fault-injection switchboard. Cheap to clone; all clones see the
+/// same faults.
+#[derive(Clone)]
+pub struct Chaos {
+    faults: Arc<watch::Sender<Faults>>,
+}
+
+impl Default for Chaos {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Chaos {
+    pub fn new() -> Self {
+        let (faults, _) = watch::channel(Faults::default());
+        Self {
+            faults: Arc::new(faults),
+        }
+    }
+
+    /// Creates a controller and starts its control endpoint and schedule
+    /// when configured. With neither, no faults are ever injected.
+    pub fn start(
+        addr: Option<SocketAddr>,
+        schedule: Option<&Path>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let chaos = Self::new();
+
+        if let Some(path) = schedule {
+            let schedule = Schedule::load(path)?;
+            info!(
+                "Loaded chaos schedule with {} steps from {}",
+                schedule.steps.len(),
+                path.display()
+            );
+            tokio::spawn(chaos.clone().run_schedule(schedule));
+        }
+
+        if let Some(addr) = addr {
+            let server = chaos.clone();
+            tokio::spawn(async move {
+                if let Err(e) = server.serve(addr).await {
+                    error!("Chaos endpoint failed: {}", e);
+                }
+            });
+        }
+
+        Ok(chaos)
+    }
+
+    pub fn faults(&self) -> Faults {
+        self.faults.borrow().clone()
+    }
+
+    pub fn apply(&self, update: &FaultUpdate) -> Result<Faults, String> {
+        update.validate()?;
+        self.faults.send_modify(|faults| {
+            if let Some(drop_percent) = update.drop_percent {
+                faults.drop_percent = drop_percent;
+            }
+            if let Some(latency_ms) = update.latency_ms {
+                faults.latency_ms = latency_ms;
+            }
+            if let Some(paused) = update.paused {
+                faults.paused = paused;
+            }
+        });
+
+        let faults = self.faults();
+        warn!(
+            drop_percent = faults.drop_percent,
+            latency_ms = faults.latency_ms,
+            paused = faults.paused,
+            "Chaos faults updated"
+        );
+        Ok(faults)
+    }
+
+    pub fn clear(&self) {
+        self.faults.send_replace(Faults::default());
+        info!("Chaos faults cleared");
+    }
+
+    /// Decides whether the next produced message should be discarded.
+    pub fn should_drop(&self) -> bool {
+        let drop_percent = self.faults.borrow().drop_percent;
+        drop_percent > 0.0 && rand::random::<f64>() * 100.0 < drop_percent
+    }
+
+    pub async fn inject_latency(&self) {
+        let latency_ms = self.faults.borrow().latency_ms;
+        if latency_ms > 0 {
+            tokio::time::sleep(Duration::from_millis(latency_ms)).await;
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.faults.borrow().paused
+    }
+
+    /// Watches the faults, for callers that must react to a change while
+    /// blocked on something else.
+    pub fn subscribe(&self) -> watch::Receiver<Faults> {
+        self.faults.subscribe()
+    }
+
+    pub async fn wait_while_paused(&self) {
+        let mut faults = self.faults.subscribe();
+        // Only fails once the sender is gone, which `self` prevents
+        let _ = faults.wait_for(|faults| !faults.paused).await;
+    }
+
+    async fn run_schedule(self, schedule: Schedule) {
+        let start = tokio::time::Instant::now();
+        for step in schedule.steps {
+            tokio::time::sleep_until(start + Duration::from_secs(step.at_secs)).await;
+            if let Err(e) = self.apply(&step.update()) {
+                warn!("Skipping chaos step at {}s: {}", step.at_secs, e);
+            }
+        }
+    }
+
+    /// Serves `GET`/`POST`/`DELETE /chaos` on `addr` until the process exits.
+    pub async fn serve(self, addr: SocketAddr) -> std::io::Result<()> {
+        let app = Router::new()
+            .route(
+                "/chaos",
+                get(get_faults).post(update_faults).delete(clear_faults),
+            )
+            .with_state(self);
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        axum::serve(listener, app).await
+    }
+}
+
+async fn get_faults(State(chaos): State<Chaos>) -> Json<Faults> {
+    Json(chaos.faults())
+}
+
+async fn update_faults(
+    State(chaos): State<Chaos>,
+    Json(update): Json<FaultUpdate>,
+) -> Result<Json<Faults>, (StatusCode, String)> {
+    chaos
+        .apply(&update)
+        .map(Json)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))
+}
+
+async fn clear_faults(State(chaos): State<Chaos>) -> Json<Faults> {
+    chaos.clear();
+    Json(chaos.faults())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// Writes `text` to a schedule file unique to the calling test.
+    fn schedule_file(name: &str, text: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("chaos-{}-{}.toml", name, std::process::id()));
+        std::fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn load_sorts_steps_by_time() {
+        let path = schedule_file(
+            "sorted",
+            "[[step]]\nat_secs = 60\npaused = true\n\
+             [[step]]\nat_secs = 10\ndrop_percent = 5.0\n\
+             [[step]]\nat_secs = 30\nlatency_ms = 250\n",
+        );
+        let schedule = Schedule::load(&path).unwrap();
+        let times: Vec<u64> = schedule.steps.iter().map(|s| s.at_secs).collect();
+        assert_eq!(times, [10, 30, 60]);
+        assert_eq!(schedule.steps[0].drop_percent, Some(5.0));
+        assert_eq!(schedule.steps[1].latency_ms, Some(250));
+        assert_eq!(schedule.steps[2].paused, Some(true));
+    }
+
+    #[test]
+    fn load_rejects_out_of_range_drop_percent() {
+        let path = schedule_file("range", "[[step]]\nat_secs = 5\ndrop_percent = 150.0\n");
+        let error = Schedule::load(&path).unwrap_err().to_string();
+        assert!(error.contains("step at 5s"), "{}", error);
+        assert!(error.contains("drop_percent"), "{}", error);
+    }
+
+    #[test]
+    fn load_rejects_unknown_keys() {
+        let path = schedule_file("typo", "[[step]]\nat_secs = 5\ndrop_pct = 50.0\n");
+        let error = Schedule::load(&path).unwrap_err().to_string();
+        assert!(error.contains("drop_pct"), "{}", error);
+    }
+
+    #[test]
+    fn apply_keeps_fields_not_in_update() {
+        let chaos = Chaos::new();
+        chaos
+            .apply(&FaultUpdate {
+                drop_percent: Some(10.0),
+                latency_ms: Some(200),
+                paused: Some(true),
+            })
+            .unwrap();
+
+        let faults = chaos
+            .apply(&FaultUpdate {
+                latency_ms: Some(50),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(
+            faults,
+            Faults {
+                drop_percent: 10.0,
+                latency_ms: 50,
+                paused: true,
+            }
+        );
+    }
+
+    #[test]
+    fn apply_rejects_invalid_update_without_changes() {
+        let chaos = Chaos::new();
+        let update = FaultUpdate {
+            drop_percent: Some(-1.0),
+            paused: Some(true),
+            ..Default::default()
+        };
+        assert!(chaos.apply(&update).is_err());
+        assert_eq!(chaos.faults(), Faults::default());
+    }
+}
@@ -0,0 +1,459 @@
+use figment::providers::{Env, Format, Serialized, Toml};
+use figment::Figment;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tracing::{info, warn};
+
+/// Settings for one service.
+///
+/// Values are layered, lowest precedence first: built-in defaults, the
+/// `[default]` table of the config file, the service's own table in that
+/// file (e.g. `[sender]`), environment variables, then command-line
+/// overrides.
+pub trait ServiceConfig:
+    Serialize + DeserializeOwned + Default + Clone + PartialEq + Send + Sync + 'static
+{
+    /// Config file table holding this service's settings.
+    const PROFILE: &'static str;
+
+    /// Environment variables read by this service, named after the fields
+    /// (`KAFKA_BROKERS` sets `kafka_brokers`).
+    const ENV_KEYS: &'static [&'static str];
+
+    /// Returns one message per invalid setting.
+    fn validate(&self) -> Vec<String>;
+}
+
+/// Backend the services produce to and consume from.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BusKind {
+    #[default]
+    Kafka,
+    Nats,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SenderConfig {
+    pub message_bus: BusKind,
+    pub nats_url: String,
+    pub kafka_brokers: String,
+    pub kafka_topic: String,
+    pub message_timeout_ms: u64,
+    pub metrics_addr: SocketAddr,
+    /// Pause between messages; reloadable.
+    pub send_interval_ms: u64,
+    /// Whether messages are produced at all; reloadable.
+    pub sending_enabled: bool,
+}
+
+impl Default for SenderConfig {
+    fn default() -> Self {
+        Self {
+            message_bus: BusKind::Kafka,
+            nats_url: "nats://localhost:4222".to_string(),
+            kafka_brokers: "localhost:9092".to_string(),
+            kafka_topic: "rust-messages".to_string(),
+            message_timeout_ms: 5000,
+            metrics_addr: ([0, 0, 0, 0], 9101).into(),
+            send_interval_ms: 100,
+            sending_enabled: true,
+        }
+    }
+}
+
+impl ServiceConfig for SenderConfig {
+    const PROFILE: &'static str = "sender";
+    const ENV_KEYS: &'static [&'static str] = &[
+        "message_bus",
+        "nats_url",
+        "kafka_brokers",
+        "kafka_topic",
+        "message_timeout_ms",
+        "metrics_addr",
+        "send_interval_ms",
+        "sending_enabled",
+    ];
+
+    fn validate(&self) -> Vec<String> {
+        let mut problems = kafka_problems(&self.kafka_brokers, &self.kafka_topic);
+        if self.message_timeout_ms == 0 {
+            problems.push("message_timeout_ms must be greater than 0".to_string());
+        }
+        if self.send_interval_ms == 0 {
+            problems.push("send_interval_ms must be greater than 0".to_string());
+        }
+        problems
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ReceiverConfig {
+    pub message_bus: BusKind,
+    pub nats_url: String,
+    pub kafka_brokers: String,
+    pub kafka_topic: String,
+    pub consumer_group: String,
+    pub metrics_addr: SocketAddr,
+    /// Log a warning for messages slower than this; reloadable.
+    pub latency_warn_ms: Option<u64>,
+}
+
+impl Default for ReceiverConfig {
+    fn default() -> Self {
+        Self {
+            message_bus: BusKind::Kafka,
+            nats_url: "nats://localhost:4222".to_string(),
+            kafka_brokers: "localhost:9092".to_string(),
+            kafka_topic: "rust-messages".to_string(),
+            consumer_group: "rust-consumer-group".to_string(),
+            metrics_addr: ([0, 0, 0, 0], 9102).into(),
+            latency_warn_ms: None,
+        }
+    }
+}
+
+impl ServiceConfig for ReceiverConfig {
+    const PROFILE: &'static str = "receiver";
+    const ENV_KEYS: &'static [&'static str] = &[
+        "message_bus",
+        "nats_url",
+        "kafka_brokers",
+        "kafka_topic",
+        "consumer_group",
+        "metrics_addr",
+        "latency_warn_ms",
+    ];
+
+    fn validate(&self) -> Vec<String> {
+        let mut problems = kafka_problems(&self.kafka_brokers, &self.kafka_topic);
+        if self.consumer_group.trim().is_empty() {
+            problems.push("consumer_group must not be empty".to_string());
+        }
+        problems
+    }
+}
+
+fn kafka_problems(brokers: &str, topic: &str) -> Vec<String> {
+    let mut problems = Vec::new();
+    if brokers.split(',').any(|broker| broker.trim().is_empty()) {
+        problems.push(format!(
+            "kafka_brokers must be a comma-separated list of host:port, got '{}'",
+            brokers
+        ));
+    }
+    if topic.trim().is_empty() {
+        problems.push("kafka_topic must not be empty".to_string());
+    }
+    problems
+}
+
+pub enum ConfigError {
+    /// A source could not be read or a value had the wrong type.
+    Load(Box<figment::Error>),
+    /// Every source parsed, but the combined settings are unusable.
+    Invalid(Vec<String>),
+    Watch(notify::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Load(e) => write!(f, "failed to load configuration: {}", e),
+            ConfigError::Invalid(problems) => {
+                write!(f, "invalid configuration:")?;
+                for problem in problems {
+                    write!(f, "\n  - {}", problem)?;
+                }
+                Ok(())
+            }
+            ConfigError::Watch(e) => write!(f, "failed to watch configuration file: {}", e),
+        }
+    }
+}
+
+// Shows the readable message when returned from `main`
+impl fmt::Debug for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Builds and validates `T` from all sources. `overrides` should skip unset
+/// fields when serialized so they do not mask lower layers.
+pub fn load<T: ServiceConfig>(
+    file: Option<&Path>,
+    overrides: &impl Serialize,
+) -> Result<T, ConfigError> {
+    let mut figment = Figment::from(Serialized::defaults(T::default()));
+    if let Some(file) = file {
+        figment = figment.merge(Toml::file_exact(file).nested());
+    }
+    let config: T = figment
+        .merge(Env::raw().only(T::ENV_KEYS).profile(T::PROFILE))
+        .merge(Serialized::from(overrides, T::PROFILE))
+        .select(T::PROFILE)
+        .extract()
+        .map_err(|e| ConfigError::Load(Box::new(e)))?;
+
+    let problems = config.validate();
+    if !problems.is_empty() {
+        return Err(ConfigError::Invalid(problems));
+    }
+    Ok(config)
+}
+
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Loads `T` and, when a file is given, reloads it whenever the file
+/// changes. Invalid edits are logged and leave the last good values in
+/// place.
+///
+/// Services read settings marked reloadable from the receiver on every use;
+/// everything else only takes effect on restart.
+pub fn watch<T: ServiceConfig>(
+    file: Option<PathBuf>,
+    overrides: impl Serialize + Send + 'static,
+) -> Result<watch::Receiver<T>, ConfigError> {
+    let config = load::<T>(file.as_deref(), &overrides)?;
+    let (tx, rx) = watch::channel(config);
+
+    let Some(file) = file else {
+        return Ok(rx);
+    };
+
+    // Watch the directory rather than the file so edits that replace the
+    // file (as most editors do) are still seen.
+    let (events_tx, mut events_rx) = mpsc::unbounded_channel();
+    let mut watcher: RecommendedWatcher = notify::recommended_watcher(move |event| {
+        let _ = events_tx.send(event);
+    })
+    .map_err(ConfigError::Watch)?;
+    let dir = match file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    watcher
+        .watch(dir, RecursiveMode::NonRecursive)
+        .map_err(ConfigError::Watch)?;
+
+    tokio::spawn(async move {
+        let _watcher = watcher;
+        while let Some(event) = events_rx.recv().await {
+            let event: notify::Event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    warn!("Configuration watcher error: {}", e);
+                    continue;
+                }
+            };
+            let touches_file = event
+                .paths
+                .iter()
+                .any(|path| path.file_name() == file.file_name());
+            if !touches_file || event.kind.is_access() {
+                continue;
+            }
+
+            // Saves often arrive as several events (truncate, write, rename);
+            // let the burst settle so a half-written file is not loaded.
+            tokio::time::sleep(RELOAD_DEBOUNCE).await;
+            while events_rx.try_recv().is_ok() {}
+
+            match load::<T>(Some(&file), &overrides) {
+                Ok(config) => {
+                    let changed = tx.send_if_modified(|current| {
+                        if *current == config {
+                            return false;
+                        }
+                        *current = config;
+                        true
+                    });
+                    if changed {
+                        info!("Reloaded configuration from {}", file.display());
+                    }
+                }
+                Err(e) => warn!("Keeping previous configuration: {}", e),
+            }
+        }
+    });
+
+    Ok(rx)
+}
+
+// `Jail` closures must return figment's large error type
+#[cfg(test)]
+#[allow(clippy::result_large_err)]
+mod tests {
+    use super::*;
+    use figment::Jail;
+    use std::collections::HashMap;
+
+    const FILE: &str = "services.toml";
+
+    fn no_overrides() -> HashMap<&'static str, u64> {
+        HashMap::new()
+    }
+
+    #[test]
+    fn layers_apply_in_order() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                FILE,
+                r#"
+                [default]
+                kafka_brokers = "file:9092"
+                kafka_topic = "default-topic"
+                metrics_addr = "127.0.0.1:9000"
+
+                [sender]
+                kafka_topic = "sender-topic"
+                message_timeout_ms = 100
+                send_interval_ms = 20
+
+                [receiver]
+                consumer_group = "not-for-the-sender"
+                "#,
+            )?;
+            jail.set_env("KAFKA_BROKERS", "env:9092");
+            jail.set_env("MESSAGE_TIMEOUT_MS", "200");
+            jail.set_env("SEND_INTERVAL_MS", "30");
+            let overrides = HashMap::from([("message_timeout_ms", 300)]);
+
+            let config = load::<SenderConfig>(Some(Path::new(FILE)), &overrides).unwrap();
+            assert_eq!(config.metrics_addr, ([127, 0, 0, 1], 9000).into());
+            assert_eq!(config.kafka_topic, "sender-topic");
+            assert_eq!(config.kafka_brokers, "env:9092");
+            assert_eq!(config.send_interval_ms, 30);
+            assert_eq!(config.message_timeout_ms, 300);
+            assert_eq!(
+                config.sending_enabled,
+                SenderConfig::default().sending_enabled
+            );
+
+            let config = load::<ReceiverConfig>(Some(Path::new(FILE)), &no_overrides()).unwrap();
+            assert_eq!(config.kafka_topic, "default-topic");
+            assert_eq!(config.kafka_brokers, "env:9092");
+            assert_eq!(config.consumer_group, "not-for-the-sender");
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn invalid_lists_every_problem() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                FILE,
+                r#"
+                [sender]
+                kafka_brokers = "a:9092,"
+                kafka_topic = " "
+                message_timeout_ms = 0
+                send_interval_ms = 0
+                "#,
+            )?;
+
+            match load::<SenderConfig>(Some(Path::new(FILE)), &no_overrides()) {
+                Err(ConfigError::Invalid(problems)) => {
+                    assert_eq!(problems.len(), 4, "{:?}", problems);
+                    let message = ConfigError::Invalid(problems).to_string();
+                    for field in [
+                        "kafka_brokers",
+                        "kafka_topic",
+                        "message_timeout_ms",
+                        "send_interval_ms",
+                    ] {
+                        assert!(message.contains(field), "{}", message);
+                    }
+                }
+                other => panic!("expected invalid configuration, got {:?}", other),
+            }
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        Jail::expect_with(|jail| {
+            jail.create_file(FILE, "[receiver]\nconsumer_grop = \"typo\"\n")?;
+
+            match load::<ReceiverConfig>(Some(Path::new(FILE)), &no_overrides()) {
+                Err(ConfigError::Load(e)) => {
+                    assert!(e.to_string().contains("consumer_grop"), "{}", e)
+                }
+                other => panic!("expected a load error, got {:?}", other),
+            }
+            Ok(())
+        });
+    }
+
+    /// Longer than the reload debounce plus file event delivery.
+    const QUIET: Duration = Duration::from_secs(1);
+
+    /// `send_interval_ms` of each configuration published until none
+    /// arrives for [`QUIET`].
+    async fn reloads(rx: &mut watch::Receiver<SenderConfig>) -> Vec<u64> {
+        let mut seen = Vec::new();
+        while let Ok(Ok(())) = tokio::time::timeout(QUIET, rx.changed()).await {
+            seen.push(rx.borrow_and_update().send_interval_ms);
+        }
+        seen
+    }
+
+    #[test]
+    fn watch_reloads_edits_and_keeps_overrides() {
+        Jail::expect_with(|jail| {
+            let file = jail.directory().join(FILE);
+            std::fs::write(&file, "[sender]\nkafka_topic = \"file-topic\"\n").unwrap();
+            let overrides = HashMap::from([("kafka_topic", "cli-topic")]);
+
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            runtime.block_on(async {
+                let mut rx = watch::<SenderConfig>(Some(file.clone()), overrides).unwrap();
+                let initial = rx.borrow().clone();
+                assert_eq!(initial.kafka_topic, "cli-topic");
+
+                // Saves closer together than the debounce load only the last
+                let writer = tokio::spawn({
+                    let file = file.clone();
+                    async move {
+                        for interval in [60, 70, 80] {
+                            let contents = format!(
+                                "[sender]\nkafka_topic = \"file-topic\"\nsend_interval_ms = {}\nsending_enabled = false\n",
+                                interval
+                            );
+                            std::fs::write(&file, contents).unwrap();
+                            tokio::time::sleep(Duration::from_millis(50)).await;
+                        }
+                    }
+                });
+                assert_eq!(reloads(&mut rx).await, [80]);
+                writer.await.unwrap();
+                assert_eq!(
+                    *rx.borrow(),
+                    SenderConfig {
+                        send_interval_ms: 80,
+                        sending_enabled: false,
+                        ..initial
+                    }
+                );
+
+                // An invalid edit keeps the previous configuration
+                std::fs::write(&file, "[sender]\nsend_interval_ms = 0\n").unwrap();
+                assert_eq!(reloads(&mut rx).await, []);
+                assert_eq!(rx.borrow().send_interval_ms, 80);
+            });
+            Ok(())
+        });
+    }
+}
@@ -0,0 +1,161 @@
+//! Runs the sender and receiver binaries against a Kafka container and
+//! checks every message the sender reports as sent is processed exactly
+//! once. Needs Docker, so it is ignored by default:
+//!
+//! ```bash
+//! cargo test -p integration-tests -- --ignored
+//! ```
+
+use rdkafka::admin::{AdminClient, AdminOptions, NewTopic, TopicReplication};
+use rdkafka::client::DefaultClientContext;
+use rdkafka::config::ClientConfig;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use testcontainers_modules::kafka::{Kafka, KAFKA_PORT};
+use testcontainers_modules::testcontainers::runners::AsyncRunner;
+
+const SEND_FOR: Duration = Duration::from_secs(5);
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Builds the service binaries into the target directory this test runs
+/// from, returning that directory.
+fn build_services() -> PathBuf {
+    let mut build = Command::new(env!("CARGO"));
+    build.args(["build", "-p", "sender", "-p", "receiver"]);
+    if !cfg!(debug_assertions) {
+        build.arg("--release");
+    }
+    let status = build.status().expect("failed to run cargo build");
+    assert!(status.success(), "building the services failed");
+
+    // The test binary lives in <target>/<profile>/deps
+    let exe = std::env::current_exe().unwrap();
+    exe.parent().unwrap().parent().unwrap().to_path_buf()
+}
+
+/// Starts a service with JSON logs, forwarding each `(key, outcome)` it
+/// logs to the returned channel.
+fn spawn_service(
+    bin_dir: &Path,
+    name: &str,
+    args: &[&str],
+) -> (Child, mpsc::Receiver<(String, String)>) {
+    let mut child = Command::new(bin_dir.join(name))
+        .args(["--log-format", "json", "--metrics-addr", "127.0.0.1:0"])
+        .args(args)
+        .env_remove("OTEL_EXPORTER_OTLP_ENDPOINT")
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap_or_else(|e| panic!("failed to start {}: {}", name, e));
+
+    let stdout = child.stdout.take().unwrap();
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            let Ok(event) = serde_json::from_str::<Value>(&line) else {
+                continue;
+            };
+            let fields = &event["fields"];
+            if let (Some(key), Some(outcome)) = (fields["key"].as_str(), fields["outcome"].as_str())
+            {
+                if tx.send((key.to_string(), outcome.to_string())).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+    (child, rx)
+}
+
+async fn create_topic(brokers: &str, topic: &str) {
+    let admin: AdminClient<DefaultClientContext> = ClientConfig::new()
+        .set("bootstrap.servers", brokers)
+        .create()
+        .unwrap();
+    let new_topic = NewTopic::new(topic, 3, TopicReplication::Fixed(1));
+    for result in admin
+        .create_topics([&new_topic], &AdminOptions::new())
+        .await
+        .unwrap()
+    {
+        result.unwrap();
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs Docker"]
+async fn every_sent_message_is_processed_exactly_once() {
+    let bin_dir = build_services();
+    let kafka = Kafka::default().start().await.unwrap();
+    let brokers = format!(
+        "127.0.0.1:{}",
+        kafka.get_host_port_ipv4(KAFKA_PORT).await.unwrap()
+    );
+    let topic = format!("integration-{}", std::process::id());
+    create_topic(&brokers, &topic).await;
+
+    let (mut receiver, processed) = spawn_service(
+        &bin_dir,
+        "receiver",
+        &["--brokers", &brokers, "--topic", &topic, "--group", &topic],
+    );
+    let (mut sender, sent) = spawn_service(
+        &bin_dir,
+        "sender",
+        &["--brokers", &brokers, "--topic", &topic],
+    );
+    tokio::time::sleep(SEND_FOR).await;
+    sender.kill().unwrap();
+    sender.wait().unwrap();
+
+    let sent: HashSet<String> = sent
+        .try_iter()
+        .filter(|(_, outcome)| outcome == "sent")
+        .map(|(key, _)| key)
+        .collect();
+    assert!(!sent.is_empty(), "the sender reported no sent messages");
+
+    // Keys the sender logged before it was killed must all arrive; one
+    // stored just before the kill may arrive without having been logged.
+    let mut counts: HashMap<String, u32> = HashMap::new();
+    let deadline = Instant::now() + RECEIVE_TIMEOUT;
+    while !sent.iter().all(|key| counts.contains_key(key)) {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match processed.recv_timeout(remaining) {
+            Ok((key, outcome)) if outcome == "processed" => *counts.entry(key).or_default() += 1,
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+    // Give redeliveries a chance to show up before stopping the receiver
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    receiver.kill().unwrap();
+    receiver.wait().unwrap();
+    for (key, outcome) in processed.try_iter() {
+        if outcome == "processed" {
+            *counts.entry(key).or_default() += 1;
+        }
+    }
+
+    let missing: Vec<_> = sent
+        .iter()
+        .filter(|key| !counts.contains_key(*key))
+        .collect();
+    assert!(
+        missing.is_empty(),
+        "{} messages never arrived: {:?}",
+        missing.len(),
+        missing
+    );
+    let duplicated: Vec<_> = counts.iter().filter(|(_, &count)| count > 1).collect();
+    assert!(
+        duplicated.is_empty(),
+        "messages processed more than once: {:?}",
+        duplicated
+    );
+}
@@ -0,0 +1,202 @@
+use axum::routing::get;
+use axum::Router;
+use prometheus::core::Collector;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// Prometheus metrics shared by every service.
+///
+/// All series carry a constant `service` label, and per-topic series a
+/// `topic` label, so dashboards can use the same queries for each binary.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    produced: IntCounterVec,
+    delivery_latency: HistogramVec,
+    producer_queue_depth: IntGauge,
+    consumed: IntCounterVec,
+    end_to_end_latency: HistogramVec,
+    consumer_lag: IntGaugeVec,
+}
+
+impl Metrics {
+    pub fn new(service: &str) -> prometheus::Result<Self> {
+        let labels = HashMap::from([("service".to_string(), service.to_string())]);
+        let registry = Registry::new_custom(None, Some(labels))?;
+
+        let produced = IntCounterVec::new(
+            Opts::new(
+                "kafka_producer_messages_total",
+                "Messages handed to the producer, by delivery outcome",
+            ),
+            &["topic", "outcome"],
+        )?;
+        let delivery_latency = HistogramVec::new(
+            HistogramOpts::new(
+                "kafka_producer_delivery_latency_seconds",
+                "Time from send until the broker acknowledged delivery",
+            ),
+            &["topic"],
+        )?;
+        let producer_queue_depth = IntGauge::new(
+            "kafka_producer_queue_depth",
+            "Messages queued in the producer awaiting delivery",
+        )?;
+        let consumed = IntCounterVec::new(
+            Opts::new(
+                "kafka_consumer_messages_total",
+                "Messages received by the consumer, by processing outcome",
+            ),
+            &["topic", "outcome"],
+        )?;
+        let end_to_end_latency = HistogramVec::new(
+            HistogramOpts::new(
+                "kafka_consumer_latency_seconds",
+                "Time from message creation until it was processed",
+            ),
+            &["topic"],
+        )?;
+        let consumer_lag = IntGaugeVec::new(
+            Opts::new(
+                "kafka_consumer_lag",
+                "Messages between the committed offset and the partition high watermark",
+            ),
+            &["topic", "partition"],
+        )?;
+
+        registry.register(Box::new(produced.clone()))?;
+        registry.register(Box::new(delivery_latency.clone()))?;
+        registry.register(Box::new(producer_queue_depth.clone()))?;
+        registry.register(Box::new(consumed.clone()))?;
+        registry.register(Box::new(end_to_end_latency.clone()))?;
+        registry.register(Box::new(consumer_lag.clone()))?;
+
+        Ok(Self {
+            registry,
+            produced,
+            delivery_latency,
+            producer_queue_depth,
+            consumed,
+            end_to_end_latency,
+            consumer_lag,
+        })
+    }
+
+    /// Counts a message the broker acknowledged, taking `delivery_latency`
+    /// to acknowledge.
+    pub fn record_sent(&self, topic: &str, delivery_latency: Duration) {
+        self.produced.with_label_values(&[topic, "sent"]).inc();
+        self.delivery_latency
+            .with_label_values(&[topic])
+            .observe(delivery_latency.as_secs_f64());
+    }
+
+    /// Counts a message the producer gave up on. Failures are left out of
+    /// the delivery latency histogram.
+    pub fn record_failed(&self, topic: &str) {
+        self.produced.with_label_values(&[topic, "failed"]).inc();
+    }
+
+    /// Counts a message deliberately discarded before producing.
+    pub fn record_dropped(&self, topic: &str) {
+        self.produced.with_label_values(&[topic, "dropped"]).inc();
+    }
+
+    pub fn set_producer_queue_depth(&self, depth: i64) {
+        self.producer_queue_depth.set(depth);
+    }
+
+    pub fn record_consumed(&self, topic: &str, outcome: &str) {
+        self.consumed.with_label_values(&[topic, outcome]).inc();
+    }
+
+    pub fn record_latency(&self, topic: &str, latency: Duration) {
+        self.end_to_end_latency
+            .with_label_values(&[topic])
+            .observe(latency.as_secs_f64());
+    }
+
+    /// Replaces the consumer lag series with one per `(topic, partition,
+    /// lag)`, removing partitions no longer listed, such as ones revoked
+    /// in a rebalance.
+    pub fn set_consumer_lags<'a>(&self, lags: impl IntoIterator<Item = (&'a str, i32, i64)>) {
+        let mut current = HashSet::new();
+        for (topic, partition, lag) in lags {
+            let partition = partition.to_string();
+            self.consumer_lag
+                .with_label_values(&[topic, &partition])
+                .set(lag);
+            current.insert((topic.to_string(), partition));
+        }
+
+        for family in self.consumer_lag.collect() {
+            for metric in family.get_metric() {
+                let label = |name: &str| {
+                    metric
+                        .get_label()
+                        .iter()
+                        .find(|l| l.get_name() == name)
+                        .map(|l| l.get_value().to_string())
+                        .unwrap_or_default()
+                };
+                let series = (label("topic"), label("partition"));
+                if !current.contains(&series) {
+                    let _ = self
+                        .consumer_lag
+                        .remove_label_values(&[&series.0, &series.1]);
+                }
+            }
+        }
+    }
+
+    /// Renders all series in the Prometheus text exposition format.
+    pub fn gather(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            return format!("# failed to encode metrics: {}\n", e);
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+
+    /// Serves `GET /metrics` on `addr` until the process exits.
+    pub async fn serve(self, addr: SocketAddr) -> std::io::Result<()> {
+        let app = Router::new().route("/metrics", get(move || async move { self.gather() }));
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        axum::serve(listener, app).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lag_lines(metrics: &Metrics) -> Vec<String> {
+        metrics
+            .gather()
+            .lines()
+            .filter(|line| line.starts_with("kafka_consumer_lag{"))
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn consumer_lags_drop_partitions_no_longer_listed() {
+        let metrics = Metrics::new("test").unwrap();
+        metrics.set_consumer_lags([("orders", 0, 5), ("orders", 1, 7)]);
+        assert_eq!(lag_lines(&metrics).len(), 2);
+
+        metrics.set_consumer_lags([("orders", 1, 3)]);
+        let lines = lag_lines(&metrics);
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].contains("partition=\"1\""), "{}", lines[0]);
+        assert!(lines[0].ends_with(" 3"), "{}", lines[0]);
+
+        metrics.set_consumer_lags([]);
+        assert!(lag_lines(&metrics).is_empty());
+    }
+}
@@ -0,0 +1,165 @@
+use chrono::Utc;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Envelope version written by this build. Readers accept any version up
+/// to and including this one.
+pub const ENVELOPE_VERSION: u32 = 1;
+
+/// A payload type that can travel inside an [`Envelope`].
+pub trait Event: Serialize + DeserializeOwned {
+    /// Value of the envelope's `type` field for this payload.
+    const TYPE: &'static str;
+}
+
+/// Wire format of every record produced to Kafka.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Envelope<T> {
+    pub version: u32,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub payload: T,
+}
+
+impl<T: Event> Envelope<T> {
+    pub fn new(payload: T) -> Self {
+        Self {
+            version: ENVELOPE_VERSION,
+            event_type: T::TYPE.to_string(),
+            payload,
+        }
+    }
+
+    pub fn encode(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    /// Parses an envelope, rejecting newer versions and other event types.
+    ///
+    /// The version and type are checked before the payload is parsed, so a
+    /// newer payload shape is reported as a version mismatch.
+    pub fn decode(json: &str) -> Result<Self, DecodeError> {
+        let envelope: Envelope<serde_json::Value> =
+            serde_json::from_str(json).map_err(DecodeError::Json)?;
+        if envelope.version > ENVELOPE_VERSION {
+            return Err(DecodeError::UnsupportedVersion(envelope.version));
+        }
+        if envelope.event_type != T::TYPE {
+            return Err(DecodeError::UnexpectedType(envelope.event_type));
+        }
+        Ok(Self {
+            version: envelope.version,
+            event_type: envelope.event_type,
+            payload: serde_json::from_value(envelope.payload).map_err(DecodeError::Json)?,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum DecodeError {
+    Json(serde_json::Error),
+    UnsupportedVersion(u32),
+    UnexpectedType(String),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Json(e) => write!(f, "invalid JSON: {}", e),
+            DecodeError::UnsupportedVersion(v) => write!(
+                f,
+                "unsupported envelope version {} (max {})",
+                v, ENVELOPE_VERSION
+            ),
+            DecodeError::UnexpectedType(t) => write!(f, "unexpected event type '{}'", t),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Message produced by the sender and consumed by the receiver.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Message {
+    pub id: String,
+    pub content: String,
+    pub timestamp: chrono::DateTime<Utc>,
+    pub counter: u64,
+}
+
+impl Event for Message {
+    const TYPE: &'static str = "message";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn sample() -> Message {
+        Message {
+            id: "550e8400-e29b-41d4-a716-446655440000".to_string(),
+            content: "Hello from Rust sender! Message #42".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2025, 7, 30, 10, 30, 45).unwrap(),
+            counter: 42,
+        }
+    }
+
+    #[test]
+    fn message_round_trips_through_envelope() {
+        let envelope = Envelope::new(sample());
+        let json = envelope.encode().unwrap();
+        assert_eq!(Envelope::<Message>::decode(&json).unwrap(), envelope);
+    }
+
+    #[test]
+    fn envelope_wire_format() {
+        let json = Envelope::new(sample()).encode().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["version"], ENVELOPE_VERSION);
+        assert_eq!(value["type"], "message");
+        assert_eq!(value["payload"]["counter"], 42);
+        assert_eq!(value["payload"]["timestamp"], "2025-07-30T10:30:45Z");
+    }
+
+    #[test]
+    fn decode_rejects_newer_version() {
+        let mut envelope = Envelope::new(sample());
+        envelope.version = ENVELOPE_VERSION + 1;
+        let json = serde_json::to_string(&envelope).unwrap();
+        assert!(matches!(
+            Envelope::<Message>::decode(&json),
+            Err(DecodeError::UnsupportedVersion(_))
+        ));
+    }
+
+    #[test]
+    fn decode_rejects_newer_version_with_other_payload_shape() {
+        let json = r#"{"version":2,"type":"message","payload":{"body":{"text":"hi"},"seq":"7"}}"#;
+        assert!(matches!(
+            Envelope::<Message>::decode(json),
+            Err(DecodeError::UnsupportedVersion(2))
+        ));
+    }
+
+    #[test]
+    fn decode_rejects_other_event_type() {
+        let mut envelope = Envelope::new(sample());
+        envelope.event_type = "transaction".to_string();
+        let json = serde_json::to_string(&envelope).unwrap();
+        assert!(matches!(
+            Envelope::<Message>::decode(&json),
+            Err(DecodeError::UnexpectedType(t)) if t == "transaction"
+        ));
+    }
+
+    #[test]
+    fn decode_rejects_bare_payload() {
+        let json = serde_json::to_string(&sample()).unwrap();
+        assert!(matches!(
+            Envelope::<Message>::decode(&json),
+            Err(DecodeError::Json(_))
+        ));
+    }
+}
@@ -0,0 +1,330 @@
+mod report;
+mod scenario;
+
+use chrono::Utc;
+use clap::Parser;
+use models::{Envelope, Message as MessagePayload};
+use rdkafka::admin::{AdminClient, AdminOptions, NewTopic, TopicReplication};
+use rdkafka::client::DefaultClientContext;
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::{Consumer, StreamConsumer};
+use rdkafka::error::KafkaError;
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::Message;
+use report::{ConsumerTotals, ProducerTotals, Report};
+use scenario::Scenario;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use telemetry::LogFormat;
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+#[derive(Parser, Debug)]
+#[command(about = "Load-test orchestrator for the Kafka services")]
+struct Args {
+    /// Scenario file (TOML)
+    scenario: PathBuf,
+
+    /// Print the report as JSON instead of text
+    #[arg(long)]
+    json: bool,
+
+    /// Log output format
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+}
+
+#[derive(Default)]
+struct ProducerStats {
+    sent: AtomicU64,
+    delivered: AtomicU64,
+    failed: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl ProducerStats {
+    fn totals(&self) -> ProducerTotals {
+        ProducerTotals {
+            sent: self.sent.load(Ordering::Relaxed),
+            delivered: self.delivered.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Sends at the scenario rate until `deadline`, then waits for outstanding
+/// deliveries.
+async fn run_producer(
+    index: usize,
+    producer: FutureProducer,
+    scenario: Arc<Scenario>,
+    topic: Arc<str>,
+    run_id: Arc<str>,
+    stats: Arc<ProducerStats>,
+    deadline: Instant,
+) {
+    let mut ticker =
+        tokio::time::interval(Duration::from_secs(1) / scenario.producers.rate_per_sec);
+    let mut in_flight = JoinSet::new();
+    let mut counter = 0u64;
+
+    while Instant::now() < deadline {
+        ticker.tick().await;
+        counter += 1;
+
+        // Injected loss: the message is counted but never produced
+        if rand::random::<f64>() * 100.0 < scenario.failures.drop_percent {
+            stats.dropped.fetch_add(1, Ordering::Relaxed);
+            continue;
+        }
+
+        let envelope = Envelope::new(MessagePayload {
+            id: format!("{}-{}-{}", run_id, index, counter),
+            content: format!("Load test message #{} from producer {}", counter, index),
+            timestamp: Utc::now(),
+            counter,
+        });
+        let payload = match envelope.encode() {
+            Ok(json) => json,
+            Err(e) => {
+                error!("Failed to serialize message: {}", e);
+                stats.failed.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+        };
+
+        stats.sent.fetch_add(1, Ordering::Relaxed);
+        let producer = producer.clone();
+        let topic = topic.clone();
+        let stats = stats.clone();
+        in_flight.spawn(async move {
+            let record = FutureRecord::to(&topic)
+                .key(&envelope.payload.id)
+                .payload(&payload);
+            match producer.send(record, Duration::from_secs(5)).await {
+                Ok(_) => {
+                    stats.delivered.fetch_add(1, Ordering::Relaxed);
+                }
+                Err((kafka_error, _)) => {
+                    warn!("Producer {} failed to send message: {}", index, kafka_error);
+                    stats.failed.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        });
+    }
+
+    while in_flight.join_next().await.is_some() {}
+}
+
+/// Consumes the run's topic until `stop` fires.
+async fn run_consumer(
+    index: usize,
+    scenario: Arc<Scenario>,
+    topic: Arc<str>,
+    group_id: String,
+    mut stop: watch::Receiver<bool>,
+) -> Result<ConsumerTotals, KafkaError> {
+    let consumer: StreamConsumer = ClientConfig::new()
+        .set("group.id", &group_id)
+        .set("bootstrap.servers", &scenario.brokers)
+        .set("enable.partition.eof", "false")
+        .set("session.timeout.ms", "6000")
+        .set("enable.auto.commit", "false")
+        .set("auto.offset.reset", "earliest")
+        .create()?;
+    consumer.subscribe(&[&*topic])?;
+
+    let mut totals = ConsumerTotals::default();
+    let delay = Duration::from_millis(scenario.failures.consumer_delay_ms);
+
+    loop {
+        tokio::select! {
+            _ = stop.changed() => break,
+            result = consumer.recv() => {
+                // Scoped so the borrowed message is released before sleeping
+                {
+                    let m = match result {
+                        Ok(m) => m,
+                        Err(e) => {
+                            warn!("Consumer {} error: {}", index, e);
+                            continue;
+                        }
+                    };
+                    let Some(Ok(payload)) = m.payload_view::<str>() else {
+                        continue;
+                    };
+                    let Ok(envelope) = Envelope::<MessagePayload>::decode(payload) else {
+                        continue;
+                    };
+
+                    totals.received += 1;
+                    totals.latencies_ms.push(
+                        Utc::now()
+                            .signed_duration_since(envelope.payload.timestamp)
+                            .num_milliseconds(),
+                    );
+                    totals.ids.insert(envelope.payload.id);
+                }
+
+                if !delay.is_zero() {
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+
+    Ok(totals)
+}
+
+fn admin_client(brokers: &str) -> Result<AdminClient<DefaultClientContext>, KafkaError> {
+    ClientConfig::new()
+        .set("bootstrap.servers", brokers)
+        .create()
+}
+
+async fn create_topic(
+    admin: &AdminClient<DefaultClientContext>,
+    topic: &str,
+    partitions: i32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let new_topic = NewTopic::new(topic, partitions, TopicReplication::Fixed(1));
+    let options = AdminOptions::new().operation_timeout(Some(Duration::from_secs(10)));
+    for result in admin.create_topics([&new_topic], &options).await? {
+        result.map_err(|(topic, code)| format!("failed to create topic {}: {}", topic, code))?;
+    }
+    info!("Created topic {} ({} partitions)", topic, partitions);
+    Ok(())
+}
+
+/// Removes the run's topic; a failure only leaves it behind, so it is
+/// logged rather than failing the run.
+async fn delete_topic(admin: &AdminClient<DefaultClientContext>, topic: &str) {
+    let options = AdminOptions::new().operation_timeout(Some(Duration::from_secs(10)));
+    match admin.delete_topics(&[topic], &options).await {
+        Ok(results) => {
+            for (topic, code) in results.into_iter().filter_map(Result::err) {
+                warn!("Failed to delete topic {}: {}", topic, code);
+            }
+        }
+        Err(e) => warn!("Failed to delete topic {}: {}", topic, e),
+    }
+}
+
+/// Runs the scenario's producers and consumers against `topic` and
+/// reports on them.
+async fn run_scenario(
+    scenario: Arc<Scenario>,
+    run_id: Arc<str>,
+    topic: Arc<str>,
+) -> Result<Report, Box<dyn std::error::Error>> {
+    let producer: FutureProducer = ClientConfig::new()
+        .set("bootstrap.servers", &scenario.brokers)
+        .set("message.timeout.ms", "5000")
+        .set("acks", "all")
+        .set("retries", "3")
+        .create()?;
+
+    let (stop_tx, stop_rx) = watch::channel(false);
+    let group_id = format!("orchestrator-{}", run_id);
+    let consumers: Vec<_> = (0..scenario.consumers.count)
+        .map(|index| {
+            tokio::spawn(run_consumer(
+                index,
+                scenario.clone(),
+                topic.clone(),
+                group_id.clone(),
+                stop_rx.clone(),
+            ))
+        })
+        .collect();
+
+    let stats = Arc::new(ProducerStats::default());
+    let started = Instant::now();
+    let deadline = started + Duration::from_secs(scenario.duration_secs);
+    let producers: Vec<_> = (0..scenario.producers.count)
+        .map(|index| {
+            tokio::spawn(run_producer(
+                index,
+                producer.clone(),
+                scenario.clone(),
+                topic.clone(),
+                run_id.clone(),
+                stats.clone(),
+                deadline,
+            ))
+        })
+        .collect();
+
+    for handle in producers {
+        handle.await?;
+    }
+    let elapsed = started.elapsed();
+
+    info!(
+        "Producers finished; draining consumers for {}s",
+        scenario.drain_secs
+    );
+    tokio::time::sleep(Duration::from_secs(scenario.drain_secs)).await;
+    let _ = stop_tx.send(true);
+
+    let mut consumer_totals = ConsumerTotals::default();
+    for handle in consumers {
+        consumer_totals.merge(handle.await??);
+    }
+
+    Ok(Report::new(
+        &scenario,
+        elapsed,
+        stats.totals(),
+        consumer_totals,
+    ))
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    // Initialize tracing and OpenTelemetry export
+    let _telemetry = telemetry::init("orchestrator", args.log_format)?;
+
+    let scenario = Arc::new(Scenario::load(&args.scenario)?);
+    let run_id: Arc<str> = Uuid::new_v4().simple().to_string().into();
+    info!(
+        "Running scenario '{}' (run {}): {} producers x {} msg/s, {} consumers, {}s",
+        scenario.name,
+        run_id,
+        scenario.producers.count,
+        scenario.producers.rate_per_sec,
+        scenario.consumers.count,
+        scenario.duration_secs
+    );
+
+    // Each run gets its own topic, so consumers reading from the earliest
+    // offset see exactly this run's messages
+    let topic: Arc<str> = format!("{}-{}", scenario.topic, run_id).into();
+    let admin = admin_client(&scenario.brokers)?;
+    create_topic(&admin, &topic, scenario.partitions).await?;
+
+    // Delete the topic whether or not the run succeeded
+    let report = run_scenario(scenario.clone(), run_id, topic.clone()).await;
+    delete_topic(&admin, &topic).await;
+    let report = report?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("{}", report);
+    }
+
+    // Returned rather than exiting, so telemetry is flushed on the way out
+    if !report.passed {
+        return Err(format!("scenario '{}' missed its targets", scenario.name).into());
+    }
+    Ok(())
+}
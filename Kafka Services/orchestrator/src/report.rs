@@ -0,0 +1,272 @@
+use crate::scenario::Scenario;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::time::Duration;
+
+/// Producer-side counts summed over all producer tasks.
+#[derive(Debug, Default)]
+pub struct ProducerTotals {
+    pub sent: u64,
+    pub delivered: u64,
+    pub failed: u64,
+    pub dropped: u64,
+}
+
+/// Consumer-side observations merged from all consumer tasks.
+#[derive(Debug, Default)]
+pub struct ConsumerTotals {
+    pub received: u64,
+    pub ids: HashSet<String>,
+    pub latencies_ms: Vec<i64>,
+}
+
+impl ConsumerTotals {
+    pub fn merge(&mut self, other: ConsumerTotals) {
+        self.received += other.received;
+        self.ids.extend(other.ids);
+        self.latencies_ms.extend(other.latencies_ms);
+    }
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct LatencySummary {
+    pub p50_ms: i64,
+    pub p95_ms: i64,
+    pub p99_ms: i64,
+    pub max_ms: i64,
+}
+
+impl LatencySummary {
+    fn from_samples(mut samples: Vec<i64>) -> Self {
+        samples.sort_unstable();
+        Self {
+            p50_ms: percentile(&samples, 50.0),
+            p95_ms: percentile(&samples, 95.0),
+            p99_ms: percentile(&samples, 99.0),
+            max_ms: samples.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+/// Nearest-rank percentile of an ascending slice.
+fn percentile(sorted: &[i64], p: f64) -> i64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[derive(Serialize, Debug)]
+pub struct Check {
+    pub name: &'static str,
+    pub target: String,
+    pub actual: String,
+    pub passed: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Report {
+    pub scenario: String,
+    pub duration_secs: f64,
+    pub producers: usize,
+    pub consumers: usize,
+    pub sent: u64,
+    pub delivered: u64,
+    pub failed: u64,
+    pub dropped: u64,
+    pub received: u64,
+    pub duplicates: u64,
+    /// Messages generated but never received, whether dropped, failed or
+    /// lost after delivery
+    pub lost: u64,
+    pub throughput: f64,
+    pub latency: LatencySummary,
+    pub checks: Vec<Check>,
+    pub passed: bool,
+}
+
+impl Report {
+    pub fn new(
+        scenario: &Scenario,
+        elapsed: Duration,
+        producers: ProducerTotals,
+        consumers: ConsumerTotals,
+    ) -> Self {
+        let unique = consumers.ids.len() as u64;
+        let generated = producers.sent + producers.dropped;
+        let lost = generated.saturating_sub(unique);
+        let loss_percent = if generated == 0 {
+            0.0
+        } else {
+            lost as f64 * 100.0 / generated as f64
+        };
+        let throughput = unique as f64 / elapsed.as_secs_f64();
+        let latency = LatencySummary::from_samples(consumers.latencies_ms);
+
+        let targets = &scenario.targets;
+        let mut checks = Vec::new();
+        if let Some(min) = targets.min_throughput {
+            checks.push(Check {
+                name: "throughput",
+                target: format!(">= {:.1} msg/s", min),
+                actual: format!("{:.1} msg/s", throughput),
+                passed: throughput >= min,
+            });
+        }
+        if let Some(max) = targets.max_p99_latency_ms {
+            checks.push(Check {
+                name: "p99 latency",
+                target: format!("<= {} ms", max),
+                actual: format!("{} ms", latency.p99_ms),
+                passed: latency.p99_ms <= max,
+            });
+        }
+        if let Some(max) = targets.max_loss_percent {
+            checks.push(Check {
+                name: "loss",
+                target: format!("<= {:.2}%", max),
+                actual: format!("{:.2}%", loss_percent),
+                passed: loss_percent <= max,
+            });
+        }
+        let passed = checks.iter().all(|c| c.passed);
+
+        Self {
+            scenario: scenario.name.clone(),
+            duration_secs: elapsed.as_secs_f64(),
+            producers: scenario.producers.count,
+            consumers: scenario.consumers.count,
+            sent: producers.sent,
+            delivered: producers.delivered,
+            failed: producers.failed,
+            dropped: producers.dropped,
+            received: consumers.received,
+            duplicates: consumers.received.saturating_sub(unique),
+            lost,
+            throughput,
+            latency,
+            checks,
+            passed,
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Scenario:   {}", self.scenario)?;
+        writeln!(
+            f,
+            "Duration:   {:.1}s ({} producers, {} consumers)",
+            self.duration_secs, self.producers, self.consumers
+        )?;
+        writeln!(
+            f,
+            "Produced:   sent={} delivered={} failed={} dropped={}",
+            self.sent, self.delivered, self.failed, self.dropped
+        )?;
+        writeln!(
+            f,
+            "Consumed:   received={} duplicates={} lost={}",
+            self.received, self.duplicates, self.lost
+        )?;
+        writeln!(f, "Throughput: {:.1} msg/s", self.throughput)?;
+        writeln!(
+            f,
+            "Latency:    p50={}ms p95={}ms p99={}ms max={}ms",
+            self.latency.p50_ms, self.latency.p95_ms, self.latency.p99_ms, self.latency.max_ms
+        )?;
+        for check in &self.checks {
+            writeln!(
+                f,
+                "  [{}] {}: {} (target {})",
+                if check.passed { "PASS" } else { "FAIL" },
+                check.name,
+                check.actual,
+                check.target
+            )?;
+        }
+        write!(
+            f,
+            "Result:     {}",
+            if self.passed { "PASS" } else { "FAIL" }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scenario(targets: &str) -> Scenario {
+        let text = format!(
+            "name = \"test\"\nduration_secs = 10\n\
+             [producers]\ncount = 2\nrate_per_sec = 10\n\
+             [consumers]\ncount = 1\n\
+             [targets]\n{}",
+            targets
+        );
+        toml::from_str(&text).unwrap()
+    }
+
+    fn received(ids: std::ops::Range<u64>, duplicates: u64) -> ConsumerTotals {
+        let ids: HashSet<String> = ids.map(|i| i.to_string()).collect();
+        ConsumerTotals {
+            received: ids.len() as u64 + duplicates,
+            latencies_ms: vec![10; ids.len()],
+            ids,
+        }
+    }
+
+    #[test]
+    fn percentile_uses_nearest_rank() {
+        let samples: Vec<i64> = (1..=100).collect();
+        assert_eq!(percentile(&samples, 50.0), 50);
+        assert_eq!(percentile(&samples, 99.0), 99);
+        assert_eq!(percentile(&samples, 100.0), 100);
+        assert_eq!(percentile(&[7], 0.0), 7);
+        assert_eq!(percentile(&[3, 9], 50.0), 3);
+        assert_eq!(percentile(&[], 99.0), 0);
+    }
+
+    #[test]
+    fn report_counts_drops_as_lost() {
+        let producers = ProducerTotals {
+            sent: 95,
+            delivered: 95,
+            failed: 0,
+            dropped: 5,
+        };
+        let report = Report::new(
+            &scenario("max_loss_percent = 1.0"),
+            Duration::from_secs(10),
+            producers,
+            received(0..95, 0),
+        );
+        assert_eq!(report.lost, 5);
+        assert_eq!(report.checks[0].actual, "5.00%");
+        assert!(!report.passed);
+    }
+
+    #[test]
+    fn report_passes_when_every_message_arrives() {
+        let producers = ProducerTotals {
+            sent: 100,
+            delivered: 100,
+            ..Default::default()
+        };
+        let report = Report::new(
+            &scenario("max_loss_percent = 0.0\nmin_throughput = 10.0\nmax_p99_latency_ms = 50"),
+            Duration::from_secs(10),
+            producers,
+            received(0..100, 3),
+        );
+        assert_eq!(report.lost, 0);
+        assert_eq!(report.duplicates, 3);
+        assert_eq!(report.throughput, 10.0);
+        assert_eq!(report.latency.p99_ms, 10);
+        assert!(report.checks.iter().all(|c| c.passed));
+        assert!(report.passed);
+    }
+}
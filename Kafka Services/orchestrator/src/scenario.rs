@@ -0,0 +1,109 @@
+use serde::Deserialize;
+use std::error::Error;
+use std::path::Path;
+
+/// Declarative load-test scenario, loaded from a TOML file.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    pub name: String,
+    #[serde(default = "default_brokers")]
+    pub brokers: String,
+    /// Prefix of the topic created for each run
+    #[serde(default = "default_topic")]
+    pub topic: String,
+    #[serde(default = "default_partitions")]
+    pub partitions: i32,
+    /// How long producers send for
+    pub duration_secs: u64,
+    /// How long consumers keep reading after producers stop
+    #[serde(default = "default_drain_secs")]
+    pub drain_secs: u64,
+    pub producers: ProducerSpec,
+    pub consumers: ConsumerSpec,
+    #[serde(default)]
+    pub failures: FailureSpec,
+    #[serde(default)]
+    pub targets: Targets,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ProducerSpec {
+    pub count: usize,
+    /// Messages per second, per producer
+    pub rate_per_sec: u32,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ConsumerSpec {
+    pub count: usize,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct FailureSpec {
+    /// Percentage of messages producers skip instead of sending
+    pub drop_percent: f64,
+    /// Artificial processing time per consumed message
+    pub consumer_delay_ms: u64,
+}
+
+/// Pass/fail thresholds; unset targets are not checked.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct Targets {
+    /// Minimum unique messages received per second
+    pub min_throughput: Option<f64>,
+    pub max_p99_latency_ms: Option<i64>,
+    /// Maximum share of generated messages never received, including
+    /// those dropped by failure injection
+    pub max_loss_percent: Option<f64>,
+}
+
+fn default_brokers() -> String {
+    "localhost:9092".to_string()
+}
+
+fn default_topic() -> String {
+    "orchestrator-load".to_string()
+}
+
+fn default_partitions() -> i32 {
+    3
+}
+
+fn default_drain_secs() -> u64 {
+    5
+}
+
+impl Scenario {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let text = std::fs::read_to_string(path)?;
+        let scenario: Scenario = toml::from_str(&text)?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.duration_secs == 0 {
+            return Err("duration_secs must be greater than 0".to_string());
+        }
+        if self.producers.count == 0 || self.producers.rate_per_sec == 0 {
+            return Err(
+                "producers.count and producers.rate_per_sec must be greater than 0".to_string(),
+            );
+        }
+        if self.partitions <= 0 {
+            return Err("partitions must be greater than 0".to_string());
+        }
+        if self.consumers.count == 0 {
+            return Err("consumers.count must be greater than 0".to_string());
+        }
+        if !(0.0..=100.0).contains(&self.failures.drop_percent) {
+            return Err("failures.drop_percent must be between 0 and 100".to_string());
+        }
+        Ok(())
+    }
+}
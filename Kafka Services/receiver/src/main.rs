@@ -1,90 +1,341 @@
+#[cfg(feature = "kafka")]
+use bus::KafkaBus;
+#[cfg(feature = "nats")]
+use bus::NatsBus;
+use bus::{Delivery, MessageBus, Subscription};
+use chaos::Chaos;
 use chrono::Utc;
-use rdkafka::config::ClientConfig;
-use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
-use rdkafka::{Message, TopicPartitionList};
-use serde::{Deserialize, Serialize};
+use clap::Parser;
+use config::{BusKind, ReceiverConfig};
+use metrics::Metrics;
+use models::{Envelope, Message as MessagePayload};
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::time::Duration;
-use tracing::{error, info, warn};
-
-#[derive(Serialize, Deserialize, Debug)]
-struct MessagePayload {
-    id: String,
-    content: String,
-    timestamp: chrono::DateTime<Utc>,
-    counter: u64,
+use telemetry::LogFormat;
+use tokio::sync::watch;
+use tracing::{error, field, info, info_span, warn, Instrument};
+
+/// How often consumer lag is exported, matching how often the backends
+/// refresh it.
+const LAG_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Parser, Debug)]
+#[command(about = "Kafka receiver service")]
+struct Args {
+    /// Log output format
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// Configuration file (TOML), reloaded when it changes
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    #[command(flatten)]
+    overrides: Overrides,
+
+    /// Address to serve the chaos control endpoint on (disabled if unset)
+    #[arg(long)]
+    chaos_addr: Option<SocketAddr>,
+
+    /// Chaos schedule (TOML) to apply while running
+    #[arg(long)]
+    chaos_schedule: Option<PathBuf>,
+}
+
+/// Command-line settings, applied over the config file and environment.
+#[derive(clap::Args, Serialize, Debug)]
+struct Overrides {
+    /// Kafka bootstrap servers
+    #[arg(long = "brokers")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kafka_brokers: Option<String>,
+
+    /// Topic to consume from
+    #[arg(long = "topic")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kafka_topic: Option<String>,
+
+    /// Consumer group to join
+    #[arg(long = "group")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    consumer_group: Option<String>,
+
+    /// Warn about messages slower than this many milliseconds
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latency_warn_ms: Option<u64>,
+
+    /// Address to serve Prometheus metrics on
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metrics_addr: Option<SocketAddr>,
+}
+
+/// Rejects a message that can never be processed, so the bus does not
+/// deliver it again.
+async fn reject<S: Subscription>(subscription: &S, m: &Delivery<S::AckToken>) {
+    if let Err(e) = subscription.reject(m).await {
+        warn!("Failed to reject message: {}", e);
+    }
+}
+
+async fn handle_message<S: Subscription>(
+    subscription: &S,
+    metrics: &Metrics,
+    m: &Delivery<S::AckToken>,
+    message_count: &mut u64,
+    latency_warn_ms: Option<u64>,
+) {
+    let key = m.key.as_deref().unwrap_or_default();
+
+    let payload = match m.payload.as_deref().map(std::str::from_utf8) {
+        None => {
+            warn!(
+                topic = m.topic.as_str(),
+                partition = m.partition,
+                offset = m.offset,
+                key,
+                outcome = "empty_payload",
+                "Received message with empty payload"
+            );
+            metrics.record_consumed(&m.topic, "empty_payload");
+            reject(subscription, m).await;
+            return;
+        }
+        Some(Ok(s)) => s,
+        Some(Err(e)) => {
+            warn!(
+                topic = m.topic.as_str(),
+                partition = m.partition,
+                offset = m.offset,
+                key,
+                outcome = "invalid_payload",
+                "Error while deserializing message payload: {:?}",
+                e
+            );
+            metrics.record_consumed(&m.topic, "invalid_payload");
+            reject(subscription, m).await;
+            return;
+        }
+    };
+
+    match Envelope::<MessagePayload>::decode(payload) {
+        Ok(Envelope {
+            payload: message_data,
+            ..
+        }) => {
+            *message_count += 1;
+            let processing_time = Utc::now();
+            let elapsed = processing_time.signed_duration_since(message_data.timestamp);
+            let latency = elapsed.num_milliseconds();
+
+            info!(
+                topic = m.topic.as_str(),
+                partition = m.partition,
+                offset = m.offset,
+                key,
+                latency_ms = latency,
+                outcome = "processed",
+                counter = message_data.counter,
+                total_received = *message_count,
+                "Received message #{}: id={}, content='{}'",
+                message_data.counter,
+                message_data.id,
+                message_data.content
+            );
+            metrics.record_consumed(&m.topic, "processed");
+            if let Some(threshold) = latency_warn_ms.filter(|&t| latency > t as i64) {
+                warn!(
+                    latency_ms = latency,
+                    "Message #{} took longer than {}ms to arrive", message_data.counter, threshold
+                );
+            }
+            // Negative under clock skew between hosts; skip those samples
+            if let Ok(latency) = elapsed.to_std() {
+                metrics.record_latency(&m.topic, latency);
+            }
+
+            // Commit the message
+            if let Err(e) = subscription.ack(m).await {
+                warn!("Failed to commit message: {}", e);
+            }
+        }
+        Err(e) => {
+            error!(
+                topic = m.topic.as_str(),
+                partition = m.partition,
+                offset = m.offset,
+                key,
+                outcome = "invalid_envelope",
+                "Failed to decode message: {} - payload: {}",
+                e,
+                payload
+            );
+            metrics.record_consumed(&m.topic, "invalid_envelope");
+            reject(subscription, m).await;
+        }
+    }
+}
+
+/// Pauses or resumes fetching as the chaos controller asks.
+fn set_paused(subscription: &impl Subscription, paused: bool) {
+    if paused {
+        if let Err(e) = subscription.pause() {
+            warn!("Failed to pause consumption: {}", e);
+        }
+        warn!("Consumption paused by chaos controller");
+    } else {
+        if let Err(e) = subscription.resume() {
+            warn!("Failed to resume consumption: {}", e);
+        }
+        info!("Consumption resumed");
+    }
+}
+
+/// Exports the lag of every assigned partition, dropping the series of
+/// partitions no longer assigned.
+async fn export_lag(subscription: &impl Subscription, metrics: &Metrics) {
+    match subscription.lag().await {
+        Ok(lags) => {
+            metrics.set_consumer_lags(lags.iter().map(|l| (l.topic.as_str(), l.partition, l.lag)))
+        }
+        Err(e) => warn!("Failed to read consumer lag: {}", e),
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize tracing
-    tracing_subscriber::fmt::init();
+    let args = Args::parse();
+
+    // Initialize tracing and OpenTelemetry export
+    let _telemetry = telemetry::init("receiver", args.log_format)?;
 
     info!("Starting Kafka receiver service...");
 
-    // Create Kafka consumer
-    let consumer: StreamConsumer = ClientConfig::new()
-        .set("group.id", "rust-consumer-group")
-        .set("bootstrap.servers", "localhost:9092")
-        .set("enable.partition.eof", "false")
-        .set("session.timeout.ms", "6000")
-        .set("enable.auto.commit", "false")
-        .set("auto.offset.reset", "earliest")
-        .create()?;
-
-    let topic = "rust-messages";
-    
+    // Load layered configuration; reloadable settings are re-read per message
+    let settings = config::watch::<ReceiverConfig>(args.config, args.overrides)?;
+    let ReceiverConfig {
+        message_bus,
+        nats_url,
+        kafka_brokers,
+        kafka_topic,
+        consumer_group,
+        metrics_addr,
+        ..
+    } = settings.borrow().clone();
+
+    // Expose Prometheus metrics
+    let metrics = Metrics::new("receiver")?;
+    tokio::spawn({
+        let metrics = metrics.clone();
+        async move {
+            if let Err(e) = metrics.serve(metrics_addr).await {
+                error!("Metrics endpoint failed: {}", e);
+            }
+        }
+    });
+
+    // Fault injection, driven by the control endpoint and/or a schedule
+    let chaos = Chaos::start(args.chaos_addr, args.chaos_schedule.as_deref())?;
+
     // Subscribe to the topic
-    consumer.subscribe(&[topic])?;
-    info!("Consumer subscribed to topic: {}", topic);
+    let topic = kafka_topic.as_str();
+    match message_bus {
+        #[cfg(feature = "kafka")]
+        BusKind::Kafka => {
+            let bus = KafkaBus::new(&kafka_brokers);
+            let subscription = bus.subscribe(topic, &consumer_group).await?;
+            info!("Consumer subscribed to topic: {}", topic);
+            receive_messages(subscription, &settings, &metrics, &chaos).await;
+        }
+        #[cfg(feature = "nats")]
+        BusKind::Nats => {
+            let bus = NatsBus::connect(&nats_url).await?;
+            let subscription = bus.subscribe(topic, &consumer_group).await?;
+            info!("Consumer subscribed to topic: {}", topic);
+            receive_messages(subscription, &settings, &metrics, &chaos).await;
+        }
+        #[cfg(not(feature = "kafka"))]
+        BusKind::Kafka => {
+            return Err(format!(
+                "cannot connect to {}: built without the kafka feature",
+                kafka_brokers
+            )
+            .into())
+        }
+        #[cfg(not(feature = "nats"))]
+        BusKind::Nats => {
+            return Err(format!(
+                "cannot connect to {}: built without the nats feature",
+                nats_url
+            )
+            .into())
+        }
+    }
+
+    Ok(())
+}
 
+/// Processes messages until the process exits.
+async fn receive_messages(
+    mut subscription: impl Subscription,
+    settings: &watch::Receiver<ReceiverConfig>,
+    metrics: &Metrics,
+    chaos: &Chaos,
+) {
     let mut message_count = 0u64;
+    let mut faults = chaos.subscribe();
+    let mut paused = false;
+    // Lag is exported on its own schedule so it keeps moving while no
+    // messages arrive, whether paused, stalled or between assignments
+    let mut lag_interval = tokio::time::interval(LAG_INTERVAL);
 
     loop {
-        match consumer.recv().await {
+        // Chaos: stop fetching while paused
+        let pause = faults.borrow_and_update().paused;
+        if pause != paused {
+            set_paused(&subscription, pause);
+            paused = pause;
+        }
+
+        let result = tokio::select! {
+            result = subscription.recv(), if !paused => result,
+            // A fault change, such as a pause, must not wait for the next message
+            Ok(()) = faults.changed() => continue,
+            _ = lag_interval.tick() => {
+                export_lag(&subscription, metrics).await;
+                continue;
+            }
+        };
+
+        match result {
             Err(e) => {
-                warn!("Kafka consumer error: {}", e);
+                warn!("Consumer error: {}", e);
                 tokio::time::sleep(Duration::from_millis(100)).await;
             }
             Ok(m) => {
-                let payload = match m.payload_view::<str>() {
-                    None => {
-                        warn!("Received message with empty payload");
-                        continue;
-                    }
-                    Some(Ok(s)) => s,
-                    Some(Err(e)) => {
-                        warn!("Error while deserializing message payload: {:?}", e);
-                        continue;
-                    }
-                };
-
-                match serde_json::from_str::<MessagePayload>(payload) {
-                    Ok(message_data) => {
-                        message_count += 1;
-                        let processing_time = Utc::now();
-                        let latency = processing_time
-                            .signed_duration_since(message_data.timestamp)
-                            .num_milliseconds();
-
-                        info!(
-                            "Received message #{}: id={}, content='{}', latency={}ms, total_received={}",
-                            message_data.counter,
-                            message_data.id,
-                            message_data.content,
-                            latency,
-                            message_count
-                        );
-
-                        // Commit the message
-                        if let Err(e) = consumer.commit_message(&m, CommitMode::Async) {
-                            warn!("Failed to commit message: {}", e);
-                        }
-                    }
-                    Err(e) => {
-                        error!("Failed to parse message JSON: {} - payload: {}", e, payload);
-                    }
-                }
+                chaos.inject_latency().await;
+
+                // Continue the sender's trace so every event logged for this
+                // message can be joined with the producing span.
+                let span = info_span!("message", trace_id = field::Empty);
+                let headers = m.headers.iter().map(|(k, v)| (k.as_str(), v.as_str()));
+                telemetry::set_parent(&span, headers);
+                span.record("trace_id", telemetry::trace_id(&span));
+
+                let latency_warn_ms = settings.borrow().latency_warn_ms;
+                handle_message(
+                    &subscription,
+                    metrics,
+                    &m,
+                    &mut message_count,
+                    latency_warn_ms,
+                )
+                .instrument(span)
+                .await;
             }
         };
     }
-}
\ No newline at end of file
+}
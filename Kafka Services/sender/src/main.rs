@@ -1,50 +1,188 @@
+#[cfg(feature = "kafka")]
+use bus::KafkaBus;
+#[cfg(feature = "nats")]
+use bus::NatsBus;
+use bus::{MessageBus, Outgoing};
+use chaos::Chaos;
 use chrono::Utc;
-use rdkafka::config::ClientConfig;
-use rdkafka::producer::{FutureProducer, FutureRecord};
-use serde::{Deserialize, Serialize};
-use std::time::Duration;
-use tracing::{error, info, warn};
+use clap::Parser;
+use config::{BusKind, SenderConfig};
+use metrics::Metrics;
+use models::{Envelope, Message};
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use telemetry::LogFormat;
+use tokio::sync::watch;
+use tracing::{error, field, info, info_span, warn, Instrument};
 use uuid::Uuid;
 
-#[derive(Serialize, Deserialize, Debug)]
-struct Message {
-    id: String,
-    content: String,
-    timestamp: chrono::DateTime<Utc>,
-    counter: u64,
+#[derive(Parser, Debug)]
+#[command(about = "Kafka sender service")]
+struct Args {
+    /// Log output format
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// Configuration file (TOML), reloaded when it changes
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    #[command(flatten)]
+    overrides: Overrides,
+
+    /// Address to serve the chaos control endpoint on (disabled if unset)
+    #[arg(long)]
+    chaos_addr: Option<SocketAddr>,
+
+    /// Chaos schedule (TOML) to apply while running
+    #[arg(long)]
+    chaos_schedule: Option<PathBuf>,
+}
+
+/// Command-line settings, applied over the config file and environment.
+#[derive(clap::Args, Serialize, Debug)]
+struct Overrides {
+    /// Kafka bootstrap servers
+    #[arg(long = "brokers")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kafka_brokers: Option<String>,
+
+    /// Topic to produce to
+    #[arg(long = "topic")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kafka_topic: Option<String>,
+
+    /// Pause between messages, in milliseconds
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    send_interval_ms: Option<u64>,
+
+    /// Address to serve Prometheus metrics on
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metrics_addr: Option<SocketAddr>,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize tracing
-    tracing_subscriber::fmt::init();
+    let args = Args::parse();
+
+    // Initialize tracing and OpenTelemetry export
+    let _telemetry = telemetry::init("sender", args.log_format)?;
 
     info!("Starting Kafka sender service...");
 
-    // Create Kafka producer
-    let producer: FutureProducer = ClientConfig::new()
-        .set("bootstrap.servers", "localhost:9092")
-        .set("message.timeout.ms", "5000")
-        .set("acks", "all")
-        .set("retries", "3")
-        .create()?;
+    // Load layered configuration; reloadable settings are re-read per message
+    let settings = config::watch::<SenderConfig>(args.config, args.overrides)?;
+    let SenderConfig {
+        message_bus,
+        nats_url,
+        kafka_brokers,
+        kafka_topic,
+        #[cfg(feature = "kafka")]
+        message_timeout_ms,
+        metrics_addr,
+        ..
+    } = settings.borrow().clone();
 
-    let topic = "rust-messages";
-    let mut counter = 0u64;
+    // Expose Prometheus metrics
+    let metrics = Metrics::new("sender")?;
+    tokio::spawn({
+        let metrics = metrics.clone();
+        async move {
+            if let Err(e) = metrics.serve(metrics_addr).await {
+                error!("Metrics endpoint failed: {}", e);
+            }
+        }
+    });
+
+    // Fault injection, driven by the control endpoint and/or a schedule
+    let chaos = Chaos::start(args.chaos_addr, args.chaos_schedule.as_deref())?;
+
+    info!("Starting to send messages to {}...", kafka_topic);
+    match message_bus {
+        #[cfg(feature = "kafka")]
+        BusKind::Kafka => {
+            let bus = KafkaBus::new(&kafka_brokers).with_message_timeout(message_timeout_ms);
+            tokio::join!(
+                send_messages(&bus, &kafka_topic, &settings, &metrics, &chaos),
+                sample_queue_depth(&bus, &metrics),
+            );
+        }
+        #[cfg(feature = "nats")]
+        BusKind::Nats => {
+            let bus = NatsBus::connect(&nats_url).await?;
+            tokio::join!(
+                send_messages(&bus, &kafka_topic, &settings, &metrics, &chaos),
+                sample_queue_depth(&bus, &metrics),
+            );
+        }
+        #[cfg(not(feature = "kafka"))]
+        BusKind::Kafka => {
+            return Err(format!(
+                "cannot connect to {}: built without the kafka feature",
+                kafka_brokers
+            )
+            .into())
+        }
+        #[cfg(not(feature = "nats"))]
+        BusKind::Nats => {
+            return Err(format!(
+                "cannot connect to {}: built without the nats feature",
+                nats_url
+            )
+            .into())
+        }
+    }
 
-    info!("Producer created successfully. Starting to send messages...");
+    Ok(())
+}
+
+/// Reports the producer queue depth once a second. Sampled apart from the
+/// send loop, which only sees the queue after each send has completed.
+async fn sample_queue_depth(bus: &impl MessageBus, metrics: &Metrics) {
+    let mut interval = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        interval.tick().await;
+        metrics.set_producer_queue_depth(bus.in_flight());
+    }
+}
+
+/// Produces a message per send interval until the process exits.
+async fn send_messages(
+    bus: &impl MessageBus,
+    topic: &str,
+    settings: &watch::Receiver<SenderConfig>,
+    metrics: &Metrics,
+    chaos: &Chaos,
+) {
+    let mut counter = 0u64;
 
     loop {
+        let (send_interval, sending_enabled) = {
+            let current = settings.borrow();
+            (
+                Duration::from_millis(current.send_interval_ms),
+                current.sending_enabled,
+            )
+        };
+        if !sending_enabled {
+            tokio::time::sleep(send_interval).await;
+            continue;
+        }
+
         counter += 1;
-        
-        let message = Message {
+
+        let envelope = Envelope::new(Message {
             id: Uuid::new_v4().to_string(),
             content: format!("Hello from Rust sender! Message #{}", counter),
             timestamp: Utc::now(),
             counter,
-        };
+        });
 
-        let payload = match serde_json::to_string(&message) {
+        let payload = match envelope.encode() {
             Ok(json) => json,
             Err(e) => {
                 error!("Failed to serialize message: {}", e);
@@ -52,23 +190,64 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         };
 
-        let record = FutureRecord::to(topic)
-            .key(&message.id)
-            .payload(&payload);
+        // Each send gets its own span; its context travels in the record
+        // headers so the receiver's processing joins the same trace.
+        let span = info_span!("send", trace_id = field::Empty);
+        span.record("trace_id", telemetry::trace_id(&span));
 
-        match producer.send(record, Duration::from_secs(5)).await {
-            Ok(delivery) => {
-                info!(
-                    "Message sent successfully: partition={}, offset={}, counter={}",
-                    delivery.0, delivery.1, counter
+        let message = Outgoing {
+            key: envelope.payload.id.clone(),
+            payload: payload.into_bytes(),
+            headers: telemetry::inject(&span),
+        };
+
+        async {
+            if chaos.should_drop() {
+                metrics.record_dropped(topic);
+                warn!(
+                    topic,
+                    key = %envelope.payload.id,
+                    outcome = "dropped",
+                    "Chaos dropped message {}",
+                    counter
                 );
+                return;
             }
-            Err((kafka_error, _)) => {
-                warn!("Failed to send message {}: {}", counter, kafka_error);
+            chaos.inject_latency().await;
+
+            let sent_at = Instant::now();
+            match bus.publish(topic, message).await {
+                Ok(receipt) => {
+                    metrics.record_sent(topic, sent_at.elapsed());
+                    info!(
+                        topic,
+                        partition = receipt.partition,
+                        offset = receipt.offset,
+                        key = %envelope.payload.id,
+                        outcome = "sent",
+                        "Message sent successfully: partition={}, offset={}, counter={}",
+                        receipt.partition,
+                        receipt.offset,
+                        counter
+                    );
+                }
+                Err(e) => {
+                    metrics.record_failed(topic);
+                    warn!(
+                        topic,
+                        key = %envelope.payload.id,
+                        outcome = "failed",
+                        "Failed to send message {}: {}",
+                        counter,
+                        e
+                    );
+                }
             }
         }
+        .instrument(span)
+        .await;
 
-        // Wait 100ms before sending next message
-        tokio::time::sleep(Duration::from_millis(100)).await;
+        // Wait before sending next message
+        tokio::time::sleep(send_interval).await;
     }
-}
\ No newline at end of file
+}
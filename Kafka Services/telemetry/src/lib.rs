@@ -0,0 +1,97 @@
+use clap::ValueEnum;
+use opentelemetry::trace::{TraceContextExt, TraceError, TracerProvider as _};
+use opentelemetry::{global, KeyValue};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::{runtime, Resource};
+use std::collections::HashMap;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, Layer};
+
+/// Log output format shared by every service's `--log-format` flag.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+/// Flushes pending spans to the exporter when dropped.
+pub struct TelemetryGuard {
+    provider: TracerProvider,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Err(e) = self.provider.shutdown() {
+            eprintln!("Failed to shut down tracer provider: {}", e);
+        }
+    }
+}
+
+/// Installs the global tracing subscriber and OpenTelemetry tracer.
+///
+/// Spans are always assigned trace IDs so they can be propagated through
+/// Kafka headers; they are only exported when `OTEL_EXPORTER_OTLP_ENDPOINT`
+/// is set.
+pub fn init(service_name: &'static str, format: LogFormat) -> Result<TelemetryGuard, TraceError> {
+    let mut builder = TracerProvider::builder()
+        .with_resource(Resource::new([KeyValue::new("service.name", service_name)]));
+
+    if std::env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT").is_some() {
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_tonic()
+            .build()?;
+        builder = builder.with_batch_exporter(exporter, runtime::Tokio);
+    }
+
+    let provider = builder.build();
+    let tracer = provider.tracer(service_name);
+
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    global::set_tracer_provider(provider.clone());
+
+    let fmt_layer = match format {
+        LogFormat::Text => fmt::layer().boxed(),
+        LogFormat::Json => fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .boxed(),
+    };
+
+    tracing_subscriber::registry()
+        .with(fmt_layer)
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .with(LevelFilter::INFO)
+        .init();
+
+    Ok(TelemetryGuard { provider })
+}
+
+/// Serializes the span's context into message headers (`traceparent`, ...).
+pub fn inject(span: &Span) -> Vec<(String, String)> {
+    let mut carrier = HashMap::new();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&span.context(), &mut carrier)
+    });
+    carrier.into_iter().collect()
+}
+
+/// Makes the context carried in message headers the parent of `span`.
+pub fn set_parent<'a>(span: &Span, headers: impl IntoIterator<Item = (&'a str, &'a str)>) {
+    let carrier: HashMap<String, String> = headers
+        .into_iter()
+        .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        .collect();
+    let cx = global::get_text_map_propagator(|propagator| propagator.extract(&carrier));
+    span.set_parent(cx);
+}
+
+/// Hex trace ID of the span, for correlating log lines with traces.
+pub fn trace_id(span: &Span) -> String {
+    span.context().span().span_context().trace_id().to_string()
+}